@@ -1,9 +1,12 @@
 use kanemi::{
     actual_synoptic_observations::{dataset, models::Station},
-    dataplatform::{api::OpenDataAPI, models::config::DatasetConfig},
+    dataplatform::{
+        api::OpenDataAPI,
+        cache::{DatasetCache, RetentionPolicy},
+        models::config::KnownDataset,
+    },
 };
 use serde::{Deserialize, Serialize};
-use std::fs;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Observation {
@@ -18,24 +21,26 @@ pub async fn get_closest_observation(
     longitude: f64,
     latitude: f64,
 ) -> Result<String, String> {
-    let dataset_config =
-        DatasetConfig::new("Actuele10mindataKNMIstations".to_string(), "2".to_string());
-    let path = "./output/observations";
-    let oda = OpenDataAPI::new(api_key, dataset_config, None);
-    let download_result = oda.download_latest_file(path, None, Some(false)).await;
+    let known_dataset = KnownDataset::ActualSynopticObservations;
+    let cache = DatasetCache::new("./output", RetentionPolicy::keep_last(1));
+    let oda = OpenDataAPI::new(api_key);
+    let download_result = cache
+        .get_latest(
+            &oda,
+            &known_dataset.config(),
+            known_dataset.update_interval(),
+        )
+        .await;
     if let Err(e) = download_result {
         return Err(e.to_string());
     }
 
-    let (_, latest_file) = download_result.unwrap();
-    let dataset = dataset::Dataset::new(latest_file.clone());
+    let latest_file = download_result.unwrap();
+    let dataset = dataset::Dataset::new(latest_file);
     if let Err(e) = dataset {
         return Err(e.to_string());
     }
 
-    // remove all files in folder except for latest file
-    remove_files_except(&latest_file, path);
-
     let dataset = dataset.unwrap();
     let (station, distance) = dataset.get_closest_station(longitude, latitude);
     let datetime = dataset
@@ -51,16 +56,3 @@ pub async fn get_closest_observation(
 
     Ok(serde_json::to_string_pretty(&observation).unwrap())
 }
-
-// remove all files in folder except for given file
-fn remove_files_except(file: &str, folder: &str) {
-    let paths = fs::read_dir(folder).unwrap();
-    for path in paths {
-        let path = path.unwrap().path();
-
-        // Check if the path is a file and not the specified file
-        if path.is_file() && path.to_str().unwrap() != file {
-            fs::remove_file(path).unwrap();
-        }
-    }
-}
@@ -5,5 +5,5 @@ export type NowcastPrediction = {
 
 export type NowcastPredictionValue = {
     datetime: string;
-    value: number;
+    value: number | null;
 };
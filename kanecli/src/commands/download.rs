@@ -1,5 +1,7 @@
 use super::help::*;
+use chrono::{DateTime, Utc};
 use clap::Args;
+use kanemi::dataplatform::api::DEFAULT_DOWNLOAD_CONCURRENCY;
 
 #[derive(Args, Debug)]
 pub struct DownloadOptions {
@@ -17,4 +19,52 @@ pub struct DownloadOptions {
 
     #[arg(long, short, help = SHORT_HELP_DATASET_VERSION)]
     pub version: String,
+
+    #[arg(long, short('t'), required = false, value_parser = parse_datetime, conflicts_with_all = ["begin", "end"], help = SHORT_HELP_DOWNLOAD_DATETIME, long_help = LONG_HELP_DOWNLOAD_DATETIME)]
+    pub datetime: Option<DateTime<Utc>>,
+
+    #[arg(long, short, required = false, value_parser = parse_datetime, requires = "end", help = SHORT_HELP_DOWNLOAD_BEGIN, long_help = LONG_HELP_DOWNLOAD_BEGIN)]
+    pub begin: Option<DateTime<Utc>>,
+
+    #[arg(long, short, required = false, value_parser = parse_datetime, requires = "begin", help = SHORT_HELP_DOWNLOAD_END, long_help = LONG_HELP_DOWNLOAD_END)]
+    pub end: Option<DateTime<Utc>>,
+
+    #[arg(long, short, required = false, default_value_t = DEFAULT_DOWNLOAD_CONCURRENCY, help = SHORT_HELP_DOWNLOAD_CONCURRENCY, long_help = LONG_HELP_DOWNLOAD_CONCURRENCY)]
+    pub concurrency: usize,
+
+    #[arg(long, required = false, conflicts_with_all = ["datetime", "begin", "end", "filename"], help = SHORT_HELP_DOWNLOAD_SYNC, long_help = LONG_HELP_DOWNLOAD_SYNC)]
+    pub sync: bool,
+
+    #[arg(long, short, required = false, default_value_t = 24, requires = "sync", help = SHORT_HELP_DOWNLOAD_WINDOW, long_help = LONG_HELP_DOWNLOAD_WINDOW)]
+    pub window: u64,
+
+    #[arg(long, short, required = false, requires = "sync", help = SHORT_HELP_DOWNLOAD_PRUNE, long_help = LONG_HELP_DOWNLOAD_PRUNE)]
+    pub prune: bool,
+
+    #[arg(long, required = false, conflicts_with_all = ["datetime", "begin", "end", "filename", "sync"], help = SHORT_HELP_DOWNLOAD_WATCH, long_help = LONG_HELP_DOWNLOAD_WATCH)]
+    pub watch: bool,
+
+    #[arg(long, required = false, env = "KNMI_API_KEY_NOTIFICATION", help = SHORT_HELP_API_KEY_NOTI, long_help = LONG_HELP_API_KEY_NOTI)]
+    pub notification_api_key: Option<String>,
+
+    // only used with --watch, no `requires` as the value can also come from the environment
+    #[arg(long, required = false, env = "KNMI_CLIENT_ID_NOTIFICATION", help = SHORT_HELP_CLIENT_ID_NOTI, long_help = LONG_HELP_CLIENT_ID_NOTI)]
+    pub client_id: Option<String>,
+
+    #[arg(long, required = false, default_value_t = 60, requires = "watch", help = SHORT_HELP_DOWNLOAD_POLL_INTERVAL, long_help = LONG_HELP_DOWNLOAD_POLL_INTERVAL)]
+    pub poll_interval: u64,
+
+    #[arg(long, required = false, requires = "watch", requires = "notification_api_key", help = SHORT_HELP_DOWNLOAD_STATE_FILE, long_help = LONG_HELP_DOWNLOAD_STATE_FILE)]
+    pub state_file: Option<String>,
+}
+
+fn parse_datetime(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|d| d.with_timezone(&Utc))
+        .map_err(|e| {
+            format!(
+                "Invalid datetime, expected RFC 3339 (2024-12-22T20:55:00Z): {}",
+                e
+            )
+        })
 }
@@ -109,11 +109,12 @@ browse to https://developer.dataplatform.knmi.nl/open-data-api#token
 Key can be set trough env var KNMI_API_KEY_OPEN_DATA"#;
 
 pub const SHORT_HELP_LOCATION: &str =
-    r#"Location as a comma-separated string "longitude,latitude""#;
-pub const LONG_HELP_LOCATION: &str = r#"Location as a comma-separated string "longitude,latitude
+    r#"Location(s) as a comma-separated string "longitude,latitude""#;
+pub const LONG_HELP_LOCATION: &str = r#"Location(s) as a comma-separated string "longitude,latitude
+Multiple locations can be separated by a space, the dataset is read once for all locations.
 
 Example: --location 5.12,52.09
-Example: --location "5.12, 52.09""#;
+Example: --location "5.12,52.09 4.89,52.37""#;
 
 pub const SHORT_HELP_OUTPUT_DIR: &str = r#"Output directory for the forecast data"#;
 pub const LONG_HELP_OUTPUT_DIR: &str = r#"Output directory for the forecast data
@@ -122,6 +123,15 @@ Default: ./output
 
 Example: --output-dir ./output"#;
 
+pub const SHORT_HELP_CACHE_DIR: &str = r#"Cache directory for the forecast data"#;
+pub const LONG_HELP_CACHE_DIR: &str = r#"Cache directory for the forecast data, files are stored in
+<dir>/<dataset>/<version>. Only the latest file is kept and a file downloaded within
+the update interval of the dataset is reused instead of requesting the API again.
+
+Default: ./output
+
+Example: --output-dir ./output"#;
+
 pub const SHORT_HELP_DOWNLOAD_OUTPUT_FILENAME: &str =
     r#"The name to use for saving the file, leave black to use original name"#;
 pub const LONG_HELP_DOWNLOAD_OUTPUT_FILENAME: &str =
@@ -145,6 +155,22 @@ pub const SHORT_HELP_DATASET_NAME: &str =
     "The name of the dataset as found on the KNMI Open Data API";
 pub const SHORT_HELP_DATASET_VERSION: &str =
     "The version of the dataset as found on the KNMI Open Data API";
+pub const LONG_HELP_DATASET_NAME_NOTI: &str = r#"The name of the dataset as found on the KNMI Open Data API.
+Can be repeated to receive notifications of multiple datasets over a single connection,
+every --name requires a matching --version.
+
+Example: --name radar_forecast --version 2.0 --name Actuele10mindataKNMIstations --version 2"#;
+pub const LONG_HELP_DATASET_VERSION_NOTI: &str = r#"The version of the dataset as found on the KNMI Open Data API.
+Can be repeated, the versions are matched with the names in the given order.
+
+Example: --name radar_forecast --version 2.0"#;
+pub const SHORT_HELP_TOPIC_NOTI: &str =
+    "Topic to subscribe to relative to dataplatform/file/v1, wildcards are allowed";
+pub const LONG_HELP_TOPIC_NOTI: &str = r##"Topic to subscribe to relative to dataplatform/file/v1, MQTT wildcards are allowed.
+Can be repeated and combined with --name and --version.
+
+Example: --topic "radar_forecast/+/created"
+Example: --topic "#""##;
 pub const SHORT_HELP_CLIENT_ID_NOTI: &str = "Unique client id for the notification service";
 pub const LONG_HELP_CLIENT_ID_NOTI: &str = r#"Unique client id for the notification service
 The client identifier is a string that identifies each MQTT client that connects to an MQTT server.
@@ -167,3 +193,124 @@ pub const LONG_HELP_CY43P1_FORECAST_PARAMETERS: &str =
 pub const SHORT_HELP_CY43P1_FORECAST_HOURS: &str = r#"The max forecast hours to return"#;
 pub const LONG_HELP_CY43P1_FORECAST_HOURS: &str =
     r#"Provide the max forecast hours to return, if not provided all hours (60) will be returned"#;
+
+pub const SHORT_HELP_DOWNLOAD_DATETIME: &str =
+    r#"Download the file valid at this datetime instead of the latest file"#;
+pub const LONG_HELP_DOWNLOAD_DATETIME: &str = r#"Download the file valid at this datetime instead of the latest file.
+The datetime is matched against the timestamp in the filename, the latest file
+which is not after the given datetime is downloaded.
+
+Example: --datetime 2024-12-22T20:55:00Z"#;
+
+pub const SHORT_HELP_DOWNLOAD_BEGIN: &str =
+    r#"Download all files from this datetime, use together with --end"#;
+pub const LONG_HELP_DOWNLOAD_BEGIN: &str = r#"Download all files with a timestamp in the filename between --begin and --end (inclusive).
+
+Example: --begin 2024-12-22T18:00:00Z --end 2024-12-22T21:00:00Z"#;
+
+pub const SHORT_HELP_DOWNLOAD_END: &str =
+    r#"Download all files until this datetime, use together with --begin"#;
+pub const LONG_HELP_DOWNLOAD_END: &str = r#"Download all files with a timestamp in the filename between --begin and --end (inclusive).
+
+Example: --begin 2024-12-22T18:00:00Z --end 2024-12-22T21:00:00Z"#;
+
+pub const SHORT_HELP_DOWNLOAD_CONCURRENCY: &str = r#"Max number of concurrent downloads"#;
+pub const LONG_HELP_DOWNLOAD_CONCURRENCY: &str = r#"Max number of files downloaded at the same time when downloading a range of files.
+
+Default: 4
+
+Example: --concurrency 8"#;
+
+pub const SHORT_HELP_DOWNLOAD_SYNC: &str =
+    r#"Keep the output directory in sync with the files of the last --window hours"#;
+pub const LONG_HELP_DOWNLOAD_SYNC: &str = r#"Keep the output directory in sync with the files of the last --window hours.
+Missing or incomplete files are downloaded and a JSON report of the changes is printed.
+
+Example: --sync --window 24 --prune"#;
+
+pub const SHORT_HELP_DOWNLOAD_WINDOW: &str =
+    r#"Number of hours to keep in sync, use together with --sync"#;
+pub const LONG_HELP_DOWNLOAD_WINDOW: &str = r#"Number of hours to keep in sync based on the timestamp in the filename, use together with --sync.
+
+Default: 24
+
+Example: --window 6"#;
+
+pub const SHORT_HELP_DOWNLOAD_PRUNE: &str =
+    r#"Remove files older than the sync window, use together with --sync"#;
+pub const LONG_HELP_DOWNLOAD_PRUNE: &str = r#"Remove files with a timestamp in the filename older than the sync window, use together with --sync.
+Files without a timestamp in the filename are never removed, pruning is only supported for
+the datasets known by kanemi as their files are recognised by the filename pattern."#;
+
+pub const SHORT_HELP_DOWNLOAD_WATCH: &str =
+    r#"Keep listening for notifications and download every new file of the dataset"#;
+pub const LONG_HELP_DOWNLOAD_WATCH: &str = r#"Keep listening for notifications and download every new file of the dataset.
+A JSON message is printed for every downloaded file, stop listening with Ctrl+C.
+Without an API key for the Notification Service the file listing of the Open Data API
+is polled every --poll-interval seconds instead.
+
+Example: --watch --notification-api-key <your-notification-api-key>"#;
+
+pub const SHORT_HELP_DOWNLOAD_POLL_INTERVAL: &str =
+    r#"Seconds between polls when watching without a notification API key"#;
+pub const LONG_HELP_DOWNLOAD_POLL_INTERVAL: &str = r#"Seconds between polls of the file listing when watching without a notification API key, use together with --watch.
+
+Default: 60"#;
+
+pub const SHORT_HELP_DOWNLOAD_STATE_FILE: &str =
+    r#"File to store handled notifications in, use together with --watch"#;
+pub const LONG_HELP_DOWNLOAD_STATE_FILE: &str = r#"File to store handled notifications in, use together with --watch and --notification-api-key.
+A notification is stored once its file is downloaded, notifications which were handled before
+are skipped, also after a restart. On start the files created since the last handled notification,
+and files of which the download failed, are downloaded first.
+
+Example: --watch --state-file ./output/notifications.json"#;
+
+pub const SHORT_HELP_RENDER_DIR: &str = r#"Directory to write the rendered images to"#;
+pub const LONG_HELP_RENDER_DIR: &str = r#"Directory to write the rendered images to, the directory is created when it does not exist.
+A PNG is written for every frame and the animation is written as GIF, the files are named after the dataset file.
+
+Default: ./render
+
+Example: --render-dir ./render"#;
+
+pub const SHORT_HELP_RENDER_SCALE: &str =
+    r#"Upscale factor, every grid pixel is rendered as scale x scale pixels"#;
+pub const LONG_HELP_RENDER_SCALE: &str = r#"Upscale factor, every grid pixel is rendered as scale x scale pixels. Between 1 and 8.
+
+Default: 1
+
+Example: --scale 2"#;
+
+pub const SHORT_HELP_RENDER_COLOR_SCALE: &str =
+    r#"Precipitation colour scale as space separated list of value:rrggbb[aa]"#;
+pub const LONG_HELP_RENDER_COLOR_SCALE: &str = r#"Precipitation colour scale as space separated list of value:rrggbb[aa], the value is in mm/h.
+A pixel gets the colour of the highest step with a value below or equal to the precipitation, pixels
+below the first step are transparent.
+
+Default: 0.1:9bc8ff 0.5:5a96f0 1:1e5adc 2:00aa3c 5:fae600 10:ff8c00 20:e60000 50:a000a0
+
+Example: --color-scale "0.1:9bc8ff80 2:00aa3c 10:ff0000""#;
+
+pub const SHORT_HELP_RENDER_FRAME_DELAY: &str =
+    r#"Milliseconds every frame of the animation is shown"#;
+pub const LONG_HELP_RENDER_FRAME_DELAY: &str = r#"Milliseconds every frame of the animation is shown
+
+Default: 500
+
+Example: --frame-delay 250"#;
+
+pub const SHORT_HELP_RENDER_NO_TIMESTAMP: &str = r#"Do not draw the datetime on the images"#;
+pub const LONG_HELP_RENDER_NO_TIMESTAMP: &str = r#"Do not draw the datetime of the frame in the top left corner of the images
+
+Example: --no-timestamp"#;
+
+pub const SHORT_HELP_RENDER_NO_FRAMES: &str = r#"Only render the animation"#;
+pub const LONG_HELP_RENDER_NO_FRAMES: &str = r#"Only render the animation, no PNG is written for every frame
+
+Example: --no-frames"#;
+
+pub const SHORT_HELP_RENDER_NO_ANIMATION: &str = r#"Only render the frames"#;
+pub const LONG_HELP_RENDER_NO_ANIMATION: &str = r#"Only render the PNG of every frame, no animation is written
+
+Example: --no-animation"#;
@@ -5,6 +5,7 @@ pub mod download;
 pub mod geocoder;
 pub mod notifications;
 pub mod nowcast_precipitation;
+pub mod nowcast_render;
 
 use clap::{Parser, Subcommand};
 
@@ -25,6 +26,9 @@ pub enum CliCommands {
     /// Dataset: Nowcast precipitation (2 hour precipitation forecast)
     NowcastPrecipitation(nowcast_precipitation::NowcastPrecipitationOptions),
 
+    /// Dataset: Nowcast precipitation, render the frames as coloured radar PNGs and an animated GIF
+    NowcastRender(nowcast_render::NowcastRenderOptions),
+
     /// Dataset: Harmonie CY43 P1 (60 hours weather forecast for the Netherlands)
     HarmonieCY43P1 {
         #[command(subcommand)]
@@ -6,11 +6,14 @@ pub struct NotificationOptions {
     #[arg(short, long, env = "KNMI_API_KEY_NOTIFICATION", help = SHORT_HELP_API_KEY_NOTI, long_help = LONG_HELP_API_KEY_NOTI)]
     pub api_key: String,
 
-    #[arg(short, long, help = SHORT_HELP_DATASET_NAME)]
-    pub name: String,
+    #[arg(short, long, required_unless_present = "topic", help = SHORT_HELP_DATASET_NAME, long_help = LONG_HELP_DATASET_NAME_NOTI)]
+    pub name: Vec<String>,
 
-    #[arg(short, long, help = SHORT_HELP_DATASET_VERSION)]
-    pub version: String,
+    #[arg(short, long, required_unless_present = "topic", help = SHORT_HELP_DATASET_VERSION, long_help = LONG_HELP_DATASET_VERSION_NOTI)]
+    pub version: Vec<String>,
+
+    #[arg(short, long, required = false, help = SHORT_HELP_TOPIC_NOTI, long_help = LONG_HELP_TOPIC_NOTI)]
+    pub topic: Vec<String>,
 
     #[arg(short, long, required = false, env = "KNMI_CLIENT_ID_NOTIFICATION", help = SHORT_HELP_CLIENT_ID_NOTI, long_help = LONG_HELP_CLIENT_ID_NOTI)]
     pub client_id: Option<String>,
@@ -6,10 +6,10 @@ pub struct NowcastPrecipitationOptions {
     #[arg(short, long, env = "KNMI_API_KEY_OPEN_DATA", help = SHORT_HELP_API_KEY_ODA, long_help = LONG_HELP_API_KEY_ODA)]
     pub api_key: String,
 
-    #[arg(short, long, env = "KNMI_LOCATION", value_parser = parse_location, help = SHORT_HELP_LOCATION, long_help = LONG_HELP_LOCATION)]
-    pub location: (f64, f64),
+    #[arg(short, long, required = true, env = "KNMI_LOCATION", value_parser = parse_location, value_delimiter = ' ', help = SHORT_HELP_LOCATION, long_help = LONG_HELP_LOCATION)]
+    pub location: Vec<(f64, f64)>,
 
-    #[arg(short, long, required = false, default_value = "./output", help = SHORT_HELP_OUTPUT_DIR, long_help = LONG_HELP_OUTPUT_DIR)]
+    #[arg(short, long, required = false, default_value = "./output", help = SHORT_HELP_CACHE_DIR, long_help = LONG_HELP_CACHE_DIR)]
     pub output_dir: String,
 
     #[arg(short, long, required = false, help = SHORT_HELP_INPUT_FILE, long_help = LONG_HELP_INPUT_FILE)]
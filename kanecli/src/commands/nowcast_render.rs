@@ -0,0 +1,57 @@
+use super::help::*;
+use clap::Args;
+use kanemi::nowcast_precipitation::render::PrecipitationColorStep;
+
+#[derive(Args, Debug)]
+pub struct NowcastRenderOptions {
+    #[arg(short, long, env = "KNMI_API_KEY_OPEN_DATA", help = SHORT_HELP_API_KEY_ODA, long_help = LONG_HELP_API_KEY_ODA)]
+    pub api_key: String,
+
+    #[arg(short, long, required = false, default_value = "./output", help = SHORT_HELP_CACHE_DIR, long_help = LONG_HELP_CACHE_DIR)]
+    pub output_dir: String,
+
+    #[arg(short, long, required = false, help = SHORT_HELP_INPUT_FILE, long_help = LONG_HELP_INPUT_FILE)]
+    pub input_file: Option<String>,
+
+    #[arg(short, long, required = false, default_value = "./render", help = SHORT_HELP_RENDER_DIR, long_help = LONG_HELP_RENDER_DIR)]
+    pub render_dir: String,
+
+    #[arg(short, long, required = false, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=8), help = SHORT_HELP_RENDER_SCALE, long_help = LONG_HELP_RENDER_SCALE)]
+    pub scale: u32,
+
+    #[arg(short, long, required = false, value_parser = parse_color_step, value_delimiter = ' ', help = SHORT_HELP_RENDER_COLOR_SCALE, long_help = LONG_HELP_RENDER_COLOR_SCALE)]
+    pub color_scale: Option<Vec<PrecipitationColorStep>>,
+
+    #[arg(long, required = false, default_value_t = 500, help = SHORT_HELP_RENDER_FRAME_DELAY, long_help = LONG_HELP_RENDER_FRAME_DELAY)]
+    pub frame_delay: u64,
+
+    #[arg(long, required = false, help = SHORT_HELP_RENDER_NO_TIMESTAMP, long_help = LONG_HELP_RENDER_NO_TIMESTAMP)]
+    pub no_timestamp: bool,
+
+    #[arg(long, required = false, conflicts_with = "no_animation", help = SHORT_HELP_RENDER_NO_FRAMES, long_help = LONG_HELP_RENDER_NO_FRAMES)]
+    pub no_frames: bool,
+
+    #[arg(long, required = false, help = SHORT_HELP_RENDER_NO_ANIMATION, long_help = LONG_HELP_RENDER_NO_ANIMATION)]
+    pub no_animation: bool,
+}
+
+/// Parse a colour step in the format `value:rrggbb` or `value:rrggbbaa`.
+fn parse_color_step(s: &str) -> Result<PrecipitationColorStep, String> {
+    let (value, color) = s
+        .split_once(':')
+        .ok_or_else(|| "Color step must be in the format 'value:rrggbb'".to_string())?;
+    let value: f64 = value.parse().map_err(|_| "Invalid value".to_string())?;
+
+    let color = color.trim_start_matches('#');
+    if !(color.len() == 6 || color.len() == 8) || !color.is_ascii() {
+        return Err("Color must be in the format rrggbb or rrggbbaa".to_string());
+    }
+
+    let mut rgba = [255; 4];
+    for (i, channel) in rgba.iter_mut().take(color.len() / 2).enumerate() {
+        *channel = u8::from_str_radix(&color[i * 2..i * 2 + 2], 16)
+            .map_err(|_| "Invalid color".to_string())?;
+    }
+
+    Ok(PrecipitationColorStep { value, color: rgba })
+}
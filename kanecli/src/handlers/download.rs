@@ -1,11 +1,19 @@
 use crate::commands::download::DownloadOptions;
 use kanemi::dataplatform::api::OpenDataAPI;
+use kanemi::dataplatform::models::config::{DatasetConfig, MqttConfig};
+use kanemi::dataplatform::notification::{NotificationService, NotificationStream};
+use kanemi::dataplatform::pipeline::{DownloadPipeline, DownloadedFile, PipelineError};
+use kanemi::dataplatform::poller::FilePoller;
+use kanemi::dataplatform::store::NotificationStore;
+use kanemi::dataplatform::sync::SyncOptions;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Serialize)]
-pub struct DownloadMessage {
+pub struct DownloadMessage<T> {
     pub success: bool,
-    pub data: String,
+    pub data: T,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -15,20 +23,116 @@ pub struct ErrorMessage {
 }
 
 pub async fn handle_command(options: DownloadOptions) {
-    let dataset_config =
-        kanemi::dataplatform::models::config::DatasetConfig::new(options.name, options.version);
+    let dataset_config = DatasetConfig::new(options.name, options.version);
 
-    let oda = OpenDataAPI::new(options.api_key, dataset_config, None);
-    let download_result = oda
-        .download_latest_file(&options.dir, options.filename, Some(true))
-        .await;
-    if let Err(e) = download_result {
-        let message = ErrorMessage {
-            success: false,
-            error: e.to_string(),
+    let oda = OpenDataAPI::new(options.api_key);
+
+    if options.watch {
+        let mut store = None;
+        // fall back to polling the file listing without a notification api key
+        let stream = match options.notification_api_key {
+            Some(notification_api_key) => {
+                let client_id = options
+                    .client_id
+                    .unwrap_or_else(|| Uuid::new_v4().to_string());
+                let mut service = NotificationService::new(MqttConfig::new_default(
+                    notification_api_key,
+                    dataset_config.clone(),
+                ));
+
+                if let Some(state_file) = options.state_file {
+                    let state_store = match NotificationStore::open(&state_file) {
+                        Ok(store) => Arc::new(store),
+                        Err(e) => {
+                            print_error(e.to_string());
+                            return;
+                        }
+                    };
+
+                    download_missed_files(
+                        &oda,
+                        &dataset_config,
+                        &state_store,
+                        &options.dir,
+                        options.concurrency,
+                    )
+                    .await;
+                    service = service.with_store(state_store.clone());
+                    store = Some(state_store);
+                }
+
+                service.stream(client_id, false)
+            }
+            None => FilePoller::new(oda.clone(), vec![dataset_config])
+                .with_interval(std::time::Duration::from_secs(options.poll_interval))
+                .stream(),
+        };
+
+        watch(oda, stream, store, options.dir, options.concurrency).await;
+
+        return;
+    }
+
+    if options.sync {
+        let sync_options = SyncOptions {
+            prune: options.prune,
+            concurrency: options.concurrency,
+            ..SyncOptions::new(std::time::Duration::from_secs(options.window * 60 * 60))
         };
+        let sync_result = oda
+            .sync_directory(&dataset_config, &options.dir, &sync_options)
+            .await;
+
+        match sync_result {
+            Ok(report) => print_message(DownloadMessage {
+                success: report.is_complete(),
+                data: report,
+            }),
+            Err(e) => print_error(e.to_string()),
+        }
+
+        return;
+    }
+
+    if let (Some(begin), Some(end)) = (options.begin, options.end) {
+        let download_result = oda
+            .download_files_between(
+                &dataset_config,
+                &options.dir,
+                begin,
+                end,
+                options.concurrency,
+                Some(true),
+            )
+            .await;
+
+        match download_result {
+            Ok(files) => print_message(DownloadMessage {
+                success: true,
+                data: files
+                    .into_iter()
+                    .map(|(_, path)| path)
+                    .collect::<Vec<String>>(),
+            }),
+            Err(e) => print_error(e.to_string()),
+        }
+
+        return;
+    }
 
-        print_message(message);
+    let download_result = match options.datetime {
+        Some(datetime) => {
+            oda.download_file_at(&dataset_config, &options.dir, datetime, Some(true))
+                .await
+        }
+        None => {
+            oda.download_latest_file(&dataset_config, &options.dir, options.filename, Some(true))
+                .await
+        }
+    };
+
+    if let Err(e) = download_result {
+        print_error(e.to_string());
         return;
     }
 
@@ -41,6 +145,86 @@ pub async fn handle_command(options: DownloadOptions) {
     print_message(message);
 }
 
+/// Download the files created since the last notification in the store.
+async fn download_missed_files(
+    oda: &OpenDataAPI,
+    dataset_config: &DatasetConfig,
+    store: &NotificationStore,
+    dir: &str,
+    concurrency: usize,
+) {
+    let missed_files = match oda.list_missed_files(dataset_config, store).await {
+        Ok(files) if files.is_empty() => return,
+        Ok(files) => files,
+        Err(e) => {
+            print_error(e.to_string());
+            return;
+        }
+    };
+
+    match oda
+        .download_dataset_files(dataset_config, missed_files, dir, concurrency, Some(false))
+        .await
+    {
+        Ok(files) => {
+            for (file, _) in &files {
+                if let Err(e) = store.acknowledge_file(dataset_config, file).await {
+                    print_error(e.to_string());
+                }
+            }
+
+            print_message(DownloadMessage {
+                success: true,
+                data: files
+                    .into_iter()
+                    .map(|(_, path)| path)
+                    .collect::<Vec<String>>(),
+            })
+        }
+        Err(e) => print_error(e.to_string()),
+    }
+}
+
+async fn watch(
+    oda: OpenDataAPI,
+    stream: NotificationStream,
+    store: Option<Arc<NotificationStore>>,
+    dir: String,
+    concurrency: usize,
+) {
+    let cancellation_token = stream.cancellation_token();
+
+    let mut pipeline = DownloadPipeline::new(oda, dir)
+        .with_concurrency(concurrency)
+        .with_hook(Arc::new(|file: DownloadedFile| {
+            print_message(DownloadMessage {
+                success: true,
+                data: file,
+            })
+        }))
+        .with_error_handler(Arc::new(|error: PipelineError| {
+            print_error(error.to_string())
+        }));
+    // downloaded files are acknowledged in the store
+    if let Some(store) = store {
+        pipeline = pipeline.with_store(store);
+    }
+
+    tokio::select! {
+        _ = pipeline.run(stream) => {},
+        _ = tokio::signal::ctrl_c() => cancellation_token.cancel(),
+    }
+}
+
+fn print_error(error: String) {
+    let message = ErrorMessage {
+        success: false,
+        error,
+    };
+
+    print_message(message);
+}
+
 fn print_message<T: Serialize>(msg: T) {
     let json = serde_json::to_string(&msg).unwrap();
     println!("{}", json);
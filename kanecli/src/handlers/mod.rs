@@ -3,3 +3,4 @@ pub mod download;
 pub mod geocoder;
 pub mod notification;
 pub mod nowcast_precipitation;
+pub mod nowcast_render;
@@ -1,11 +1,11 @@
 use crate::commands::notifications::NotificationOptions;
+use futures::StreamExt;
 use kanemi::dataplatform::models::{
-    config::{DatasetConfig, MqttConfig},
+    config::{DatasetConfig, MqttConfig, TopicSubscription},
     response::NotificationReponse,
 };
-use kanemi::errors::NotificationError;
+use kanemi::dataplatform::notification::{NotificationEvent, NotificationService};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
 use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -28,8 +28,20 @@ pub async fn handle_command(options: NotificationOptions) {
         Uuid::new_v4().to_string()
     };
 
-    let dataset_config = DatasetConfig::new(options.name, options.version);
-    if let Err(e) = run(options.api_key, client_id, dataset_config).await {
+    if options.name.len() != options.version.len() {
+        eprintln!("Error: every --name requires a matching --version");
+        return;
+    }
+
+    let mut subscriptions: Vec<TopicSubscription> = options
+        .name
+        .into_iter()
+        .zip(options.version)
+        .map(|(name, version)| TopicSubscription::Dataset(DatasetConfig::new(name, version)))
+        .collect();
+    subscriptions.extend(options.topic.into_iter().map(TopicSubscription::Topic));
+
+    if let Err(e) = run(options.api_key, client_id, subscriptions).await {
         eprintln!("Error: {}", e);
     }
 }
@@ -37,38 +49,52 @@ pub async fn handle_command(options: NotificationOptions) {
 pub async fn run(
     api_key: String,
     client_id: String,
-    dataset_config: DatasetConfig,
+    subscriptions: Vec<TopicSubscription>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mqtt_config = MqttConfig::new_default(api_key, dataset_config);
-    let mut notification_service =
-        kanemi::dataplatform::notification::NotificationService::new(mqtt_config);
-
-    let message_handler = Arc::new(|topic: String, payload: NotificationReponse| {
-        let message = NotificationMessage {
-            success: true,
-            topic,
-            data: payload,
-        };
-
-        print_notification(message);
-    });
-
-    let error_handler = Arc::new(|error: NotificationError| {
-        let message = ErrorMessage {
-            success: false,
-            error: error.to_string(),
-        };
+    if subscriptions.is_empty() {
+        return Err("No datasets or topics to subscribe to".into());
+    }
 
-        print_notification(message);
-    });
+    let mqtt_config = MqttConfig::new_default_with_subscriptions(api_key, subscriptions);
+    let notification_service = NotificationService::new(mqtt_config);
+    let mut stream = notification_service.stream(client_id, false);
 
-    notification_service
-        .start(client_id.to_string(), false, message_handler, error_handler)
-        .await?;
+    loop {
+        tokio::select! {
+            event = stream.next() => match event {
+                Some(event) => handle_event(event),
+                None => break,
+            },
+            _ = tokio::signal::ctrl_c() => {
+                stream.stop();
+            }
+        }
+    }
 
     Ok(())
 }
 
+fn handle_event(event: NotificationEvent) {
+    match event {
+        NotificationEvent::Notification {
+            topic,
+            notification,
+            ..
+        } => print_notification(NotificationMessage {
+            success: true,
+            topic,
+            data: notification,
+        }),
+        NotificationEvent::ParseError { error, .. } | NotificationEvent::Error(error) => {
+            print_notification(ErrorMessage {
+                success: false,
+                error: error.to_string(),
+            })
+        }
+        NotificationEvent::StateChanged(_) => {}
+    }
+}
+
 fn print_notification<T: Serialize>(notification: T) {
     let json = serde_json::to_string(&notification).unwrap();
     println!("{}", json);
@@ -1,19 +1,21 @@
 use crate::commands::nowcast_precipitation::NowcastPrecipitationOptions;
 use kanemi::{
-    dataplatform::{api::OpenDataAPI, models::config::DatasetConfig},
-    nowcast_precipitation::dataset,
+    dataplatform::{
+        api::OpenDataAPI,
+        cache::{DatasetCache, RetentionPolicy},
+        models::config::{DatasetConfig, KnownDataset},
+    },
+    nowcast_precipitation::{dataset, models::PrecipitationForecast},
 };
 
 pub async fn handle_command(options: NowcastPrecipitationOptions) {
-    let dataset_config = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
-    let (longitude, latitude) = options.location;
+    let dataset_config = KnownDataset::NowcastPrecipitation.config();
     get_forecast(
         options.api_key,
         dataset_config,
         options.input_file,
         options.output_dir,
-        longitude,
-        latitude,
+        options.location,
     )
     .await;
 }
@@ -23,40 +25,43 @@ pub async fn get_forecast(
     dataset_config: DatasetConfig,
     input_file: Option<String>,
     output_dir: String,
-    longitude: f64,
-    latitude: f64,
+    locations: Vec<(f64, f64)>,
 ) {
     // if input file is provided, directly load and print data
     if let Some(input_file) = input_file {
-        load_and_print_data(input_file, longitude, latitude);
+        load_and_print_data(input_file, &locations);
         return;
     }
 
-    print_from_download(api_key, dataset_config, output_dir, longitude, latitude).await;
+    print_from_download(api_key, dataset_config, output_dir, &locations).await;
 }
 
 async fn print_from_download(
     api_key: String,
     dataset_config: DatasetConfig,
     output_dir: String,
-    longitude: f64,
-    latitude: f64,
+    locations: &[(f64, f64)],
 ) {
-    let oda = OpenDataAPI::new(api_key, dataset_config, None);
-    let download_result = oda
-        .download_latest_file(&output_dir, None, Some(false))
+    let oda = OpenDataAPI::new(api_key);
+    let cache = DatasetCache::new(output_dir, RetentionPolicy::keep_last(1));
+    let download_result = cache
+        .get_latest(
+            &oda,
+            &dataset_config,
+            KnownDataset::NowcastPrecipitation.update_interval(),
+        )
         .await;
     if let Err(e) = download_result {
         eprintln!("Error: {}", e);
         return;
     }
 
-    let (_, latest_download_url) = download_result.unwrap();
+    let latest_download_url = download_result.unwrap();
 
-    load_and_print_data(latest_download_url, longitude, latitude);
+    load_and_print_data(latest_download_url, locations);
 }
 
-fn load_and_print_data(filename: String, longitude: f64, latitude: f64) {
+fn load_and_print_data(filename: String, locations: &[(f64, f64)]) {
     let dataset = dataset::Dataset::new(filename);
     if let Err(e) = dataset {
         eprintln!("Error: {}", e);
@@ -64,13 +69,19 @@ fn load_and_print_data(filename: String, longitude: f64, latitude: f64) {
     }
 
     let dataset = dataset.unwrap();
-    let forecast = dataset.get_forecast(longitude, latitude);
-    if let Err(e) = forecast {
+    let forecasts = dataset.get_forecasts(locations);
+    if let Err(e) = forecasts {
         eprintln!("Error: {}", e);
         return;
     }
 
-    let forecast = forecast.unwrap();
+    for (forecast, (longitude, latitude)) in forecasts.unwrap().into_iter().zip(locations) {
+        print_forecast(forecast, *longitude, *latitude);
+    }
+}
+
+fn print_forecast(forecast: PrecipitationForecast, longitude: f64, latitude: f64) {
+    let summary = forecast.summary();
     let date_time_first = forecast.datetime;
     let values = forecast.values;
 
@@ -88,6 +99,12 @@ fn load_and_print_data(filename: String, longitude: f64, latitude: f64) {
     for value in values {
         let hhmm = value.datetime.split('T').collect::<Vec<&str>>()[1];
         print!("\x1b[33m{:<10}\x1b[0m", hhmm);
-        println!("\x1b[33m{:<10}\x1b[0m", value.value);
+        match value.value {
+            Some(value) => println!("\x1b[33m{:<10}\x1b[0m", value),
+            None => println!("\x1b[33m{:<10}\x1b[0m", "-"),
+        }
     }
+
+    println!("\x1b[92m-----------------------------------\x1b[0m");
+    println!("\x1b[1m{}\x1b[0m", summary.description);
 }
@@ -0,0 +1,92 @@
+use crate::commands::nowcast_render::NowcastRenderOptions;
+use kanemi::{
+    dataplatform::{
+        api::OpenDataAPI,
+        cache::{DatasetCache, RetentionPolicy},
+        models::config::KnownDataset,
+    },
+    nowcast_precipitation::{
+        dataset,
+        render::{ColorScale, RenderOptions},
+    },
+};
+use std::path::Path;
+use std::time::Duration;
+
+pub async fn handle_command(options: NowcastRenderOptions) {
+    let input_file = match options.input_file.clone() {
+        Some(input_file) => input_file,
+        None => {
+            let known_dataset = KnownDataset::NowcastPrecipitation;
+            let oda = OpenDataAPI::new(options.api_key.clone());
+            let cache = DatasetCache::new(&options.output_dir, RetentionPolicy::keep_last(1));
+            let download_result = cache
+                .get_latest(
+                    &oda,
+                    &known_dataset.config(),
+                    known_dataset.update_interval(),
+                )
+                .await;
+
+            match download_result {
+                Ok(latest_file) => latest_file,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return;
+                }
+            }
+        }
+    };
+
+    render(input_file, options);
+}
+
+fn render(input_file: String, options: NowcastRenderOptions) {
+    let dataset = dataset::Dataset::new(input_file.clone());
+    if let Err(e) = dataset {
+        eprintln!("Error: {}", e);
+        return;
+    }
+
+    // the frames and the animation are rendered from the same images
+    let dataset = dataset.unwrap().with_image_cache();
+    let render_options = RenderOptions {
+        color_scale: options.color_scale.map(ColorScale::new).unwrap_or_default(),
+        upscale_factor: options.scale,
+        timestamp: !options.no_timestamp,
+        frame_delay: Duration::from_millis(options.frame_delay),
+        ..Default::default()
+    };
+
+    if let Err(e) = std::fs::create_dir_all(&options.render_dir) {
+        eprintln!("Error: Failed to create {}: {}", options.render_dir, e);
+        return;
+    }
+
+    if !options.no_frames {
+        match dataset.render_frames(&options.render_dir, &render_options) {
+            Ok(paths) => {
+                for path in paths {
+                    println!("{}", path.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return;
+            }
+        }
+    }
+
+    if !options.no_animation {
+        let name = Path::new(&input_file)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "nowcast".to_string());
+        let path = Path::new(&options.render_dir).join(format!("{}.gif", name));
+
+        match dataset.render_animation(&path, &render_options) {
+            Ok(()) => println!("{}", path.display()),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+}
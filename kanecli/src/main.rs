@@ -17,6 +17,9 @@ async fn main() {
         CliCommands::NowcastPrecipitation(forecast) => {
             handlers::nowcast_precipitation::handle_command(forecast).await;
         }
+        CliCommands::NowcastRender(render) => {
+            handlers::nowcast_render::handle_command(render).await;
+        }
         CliCommands::Notifications(notification) => {
             handlers::notification::handle_command(notification).await;
         }
@@ -1,63 +1,138 @@
 use super::models::{self};
-use crate::errors::ApiError;
-use models::config::DatasetConfig;
-use models::response::{FilesResponse, UrlResponse};
-use reqwest::Client;
-use std::path::Path;
-use tokio::fs::{self, File};
-use tokio::io::AsyncWriteExt;
+use crate::errors::{ApiError, HttpStatusError};
+use crate::utils::timestamp_from_filename;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use models::config::{BackoffPolicy, DatasetConfig};
+use models::metadata::{DatasetMetadata, DatasetVersionMetadata};
+use models::options::{ListFilesOptions, OrderBy, Sorting};
+use models::response::{
+    ErrorResponse, File as DatasetFile, FilesResponse, NotificationData, RateLimit, UrlResponse,
+};
+use reqwest::header::{HeaderMap, RANGE, RETRY_AFTER};
+use reqwest::{Client, Proxy, RequestBuilder, Response, StatusCode};
+use std::fs::Metadata;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
 
+/// Files for a historical timestamp are searched within this window (in hours) around the
+/// requested timestamp based on their creation date, filenames are used to select the file.
+const HISTORY_SEARCH_WINDOW_HOURS: i64 = 6;
+
+/// Default number of concurrent downloads when downloading multiple files.
+pub const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 4;
+
+/// Progress of a running download.
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    /// Path the file is written to
+    pub filepath: String,
+    /// Number of bytes on disk, including bytes of a resumed partial download
+    pub downloaded_bytes: u64,
+    /// Total size of the file if known
+    pub total_bytes: Option<u64>,
+}
+
+pub type DownloadProgressHandler = Arc<dyn Fn(DownloadProgress) + Send + Sync>;
+
+/// Base URL of the KNMI Open Data API.
+pub const DEFAULT_BASE_URL: &str = "https://api.dataplatform.knmi.nl/open-data/v1";
+
+/// User agent sent with every request unless a custom user agent or client is configured.
+pub const DEFAULT_USER_AGENT: &str = concat!("kanemi/", env!("CARGO_PKG_VERSION"));
+
+/// Default timeout for connecting to the API or the download host.
+pub const DEFAULT_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Default timeout for a single read, large downloads are not limited in their total duration.
+pub const DEFAULT_READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Client for the KNMI Open Data API. A single instance can be used for any number of
+/// datasets, cloning it is cheap and shares the connection pool and rate limit state.
+#[derive(Clone)]
 pub struct OpenDataAPI {
     base_url: String,
-    dataset_config: DatasetConfig,
     api_key: String,
+    client: Client,
+    progress_handler: Option<DownloadProgressHandler>,
+    retry_policy: BackoffPolicy,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
 }
 
 impl OpenDataAPI {
-    pub fn new(
-        api_key: String,
-        dataset_config: DatasetConfig,
-        custom_base_url: Option<String>,
-    ) -> Self {
+    /// Create a client with the default settings, use `OpenDataAPI::builder` to configure
+    /// the client.
+    pub fn new(api_key: String) -> Self {
         OpenDataAPI {
-            base_url: custom_base_url
-                .unwrap_or_else(|| "https://api.dataplatform.knmi.nl/open-data/v1".to_string()),
+            base_url: DEFAULT_BASE_URL.to_string(),
             api_key,
-            dataset_config,
+            client: default_client(),
+            progress_handler: None,
+            retry_policy: BackoffPolicy::default(),
+            rate_limit: Arc::new(Mutex::new(None)),
         }
     }
-    fn get_latest_file_url_and_params(&self, max_files: i8) -> (String, [(&str, String); 3]) {
-        let url = format!(
-            "{}/datasets/{}/versions/{}/files",
-            &self.base_url, &self.dataset_config.dataset_name, &self.dataset_config.version
-        );
 
-        let query_params = [
-            ("maxKeys", max_files.to_string()),
-            ("orderBy", "created".to_string()),
-            ("sorting", "desc".to_string()),
-        ];
+    /// Returns a builder to configure the HTTP client, base URL, retry policy and
+    /// progress handler.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kanemi::dataplatform::api::OpenDataAPI;
+    /// use std::time::Duration;
+    ///
+    /// # fn example() -> Result<(), kanemi::errors::ApiError> {
+    /// let oda = OpenDataAPI::builder()
+    ///     .api_key("api-key".to_string())
+    ///     .connect_timeout(Duration::from_secs(5))
+    ///     .user_agent("my-app/1.0".to_string())
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> OpenDataAPIBuilder {
+        OpenDataAPIBuilder::default()
+    }
+
+    /// Returns the rate limit reported by the last API response, None if no request has
+    /// been made yet or the API did not report a rate limit.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit.lock().unwrap().clone()
+    }
+
+    fn get_dataset_url(&self, dataset_name: &str) -> String {
+        format!("{}/datasets/{}", &self.base_url, dataset_name)
+    }
+
+    fn get_dataset_version_url(&self, dataset: &DatasetConfig) -> String {
+        format!(
+            "{}/datasets/{}/versions/{}",
+            &self.base_url, &dataset.dataset_name, &dataset.version
+        )
+    }
 
-        (url, query_params)
+    fn get_files_url(&self, dataset: &DatasetConfig) -> String {
+        format!(
+            "{}/datasets/{}/versions/{}/files",
+            &self.base_url, &dataset.dataset_name, &dataset.version
+        )
     }
 
-    fn get_file_download_url(&self, filename: &str) -> String {
+    pub(crate) fn get_file_download_url(&self, dataset: &DatasetConfig, filename: &str) -> String {
         format!(
             "{}/datasets/{}/versions/{}/files/{}/url",
-            &self.base_url,
-            &self.dataset_config.dataset_name,
-            &self.dataset_config.version,
-            filename
+            &self.base_url, &dataset.dataset_name, &dataset.version, filename
         )
     }
 
     fn create_get_request(
         &self,
         url: String,
-        query_params: Option<&[(&str, String); 3]>,
+        query_params: Option<&[(&str, String)]>,
     ) -> reqwest::RequestBuilder {
-        let client = Client::new();
-        let request = client.get(url).header("Authorization", &self.api_key);
+        let request = self.client.get(url).header("Authorization", &self.api_key);
 
         match query_params {
             Some(params) => request.query(params),
@@ -65,14 +140,146 @@ impl OpenDataAPI {
         }
     }
 
-    /// Return the latest file from the dataset
-    pub async fn get_latest_files(&self, max_files: i8) -> Result<FilesResponse, ApiError> {
-        let (url, query_params) = self.get_latest_file_url_and_params(max_files);
+    /// Send the request, requests failing because of connection errors, rate limiting or
+    /// server errors are retried according to the retry policy. The Retry-After header
+    /// is honoured when present.
+    ///
+    /// The last response is returned when all retries failed, see `check_status`.
+    ///
+    /// # Errors
+    /// - `ApiError::FetchError`: If the request fails after all retries.
+    async fn send_with_retry(&self, request: RequestBuilder) -> Result<Response, ApiError> {
+        let mut retries = 0;
+
+        loop {
+            let attempt = request.try_clone().ok_or_else(|| {
+                ApiError::FetchError("Request can not be cloned for retrying".to_string())
+            })?;
+
+            let delay = match attempt.send().await {
+                Ok(response) => {
+                    self.update_rate_limit(response.headers());
+
+                    let status = response.status();
+                    let retryable =
+                        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+                    if !retryable {
+                        return Ok(response);
+                    }
+
+                    if !self.retry_policy.should_retry(retries) {
+                        return Ok(response);
+                    }
+
+                    parse_retry_after(response.headers())
+                        .unwrap_or_else(|| self.retry_policy.delay(retries))
+                }
+                Err(e) => {
+                    let retryable = e.is_connect() || e.is_timeout() || e.is_request();
+                    if !retryable || !self.retry_policy.should_retry(retries) {
+                        return Err(ApiError::FetchError(e.to_string()));
+                    }
+
+                    self.retry_policy.delay(retries)
+                }
+            };
+
+            tokio::time::sleep(delay).await;
+            retries += 1;
+        }
+    }
+
+    /// Store the rate limit reported in the response headers, responses without
+    /// rate limit headers (such as file downloads) are ignored.
+    fn update_rate_limit(&self, headers: &HeaderMap) {
+        if let Some(rate_limit) = parse_rate_limit(headers) {
+            *self.rate_limit.lock().unwrap() = Some(rate_limit);
+        }
+    }
+
+    /// Return the metadata of a dataset such as its description, licence and versions.
+    pub async fn get_dataset_metadata(
+        &self,
+        dataset_name: &str,
+    ) -> Result<DatasetMetadata, ApiError> {
+        let url = self.get_dataset_url(dataset_name);
+        let response = self
+            .send_with_retry(self.create_get_request(url, None))
+            .await?;
+        let response = check_status(response).await?;
+
+        let data: DatasetMetadata = response
+            .json()
+            .await
+            .map_err(|e| ApiError::MetadataResponseParseError(e.to_string()))?;
+
+        Ok(data)
+    }
+
+    /// Return the metadata of a dataset version such as the number of files and the
+    /// update frequency.
+    pub async fn get_dataset_version_metadata(
+        &self,
+        dataset: &DatasetConfig,
+    ) -> Result<DatasetVersionMetadata, ApiError> {
+        let url = self.get_dataset_version_url(dataset);
         let response = self
-            .create_get_request(url, Some(&query_params))
-            .send()
+            .send_with_retry(self.create_get_request(url, None))
+            .await?;
+        let response = check_status(response).await?;
+
+        let data: DatasetVersionMetadata = response
+            .json()
             .await
-            .map_err(|e| ApiError::FetchError(e.to_string()))?;
+            .map_err(|e| ApiError::MetadataResponseParseError(e.to_string()))?;
+
+        Ok(data)
+    }
+
+    /// Check that the dataset version exists and is accessible with the API key before
+    /// downloading from it, returns the version metadata.
+    ///
+    /// # Errors
+    /// - `ApiError::ConfigurationError`: If the dataset or version does not exist.
+    /// - Any other error returned by `get_dataset_version_metadata`.
+    pub async fn validate_dataset(
+        &self,
+        dataset: &DatasetConfig,
+    ) -> Result<DatasetVersionMetadata, ApiError> {
+        match self.get_dataset_version_metadata(dataset).await {
+            Err(ApiError::NotFound(_)) => Err(ApiError::ConfigurationError(format!(
+                "Dataset {} version {} does not exist",
+                dataset.dataset_name, dataset.version
+            ))),
+            result => result,
+        }
+    }
+
+    /// Return the latest files from the dataset ordered by creation date
+    pub async fn get_latest_files(
+        &self,
+        dataset: &DatasetConfig,
+        max_files: i8,
+    ) -> Result<FilesResponse, ApiError> {
+        let options = ListFilesOptions::latest(max_files.max(1) as u32);
+        self.list_files(dataset, &options, None).await
+    }
+
+    /// Return a single page of the file listing, use the `next_page_token` from the previous
+    /// response to fetch the next page. The filename prefix filter is not applied to the page.
+    pub async fn list_files(
+        &self,
+        dataset: &DatasetConfig,
+        options: &ListFilesOptions,
+        next_page_token: Option<&str>,
+    ) -> Result<FilesResponse, ApiError> {
+        let query_params = options.construct_query(next_page_token);
+        let response = self
+            .send_with_retry(
+                self.create_get_request(self.get_files_url(dataset), Some(&query_params)),
+            )
+            .await?;
+        let response = check_status(response).await?;
 
         let data: FilesResponse = response
             .json()
@@ -82,14 +289,89 @@ impl OpenDataAPI {
         Ok(data)
     }
 
+    /// Return a stream of all files in the dataset version matching the given options.
+    /// Pages are requested lazily while the stream is consumed until the listing is
+    /// no longer truncated.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use futures::TryStreamExt;
+    /// use kanemi::dataplatform::api::OpenDataAPI;
+    /// use kanemi::dataplatform::models::config::DatasetConfig;
+    /// use kanemi::dataplatform::models::options::ListFilesOptions;
+    ///
+    /// # async fn example() -> Result<(), kanemi::errors::ApiError> {
+    /// let dataset_config = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+    /// let oda = OpenDataAPI::new("api-key".to_string());
+    /// let files: Vec<_> = oda
+    ///     .list_all_files(&dataset_config, ListFilesOptions::default())
+    ///     .try_collect()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_all_files<'a>(
+        &'a self,
+        dataset: &'a DatasetConfig,
+        options: ListFilesOptions,
+    ) -> BoxStream<'a, Result<DatasetFile, ApiError>> {
+        // state: (next page token, finished)
+        let pages = stream::try_unfold((None::<String>, false), move |(token, done)| {
+            let options = options.clone();
+            async move {
+                if done {
+                    return Ok(None);
+                }
+
+                let page = self.list_files(dataset, &options, token.as_deref()).await?;
+                let next_token = page.next_page_token.filter(|t| !t.is_empty());
+                let done = !page.is_truncated || next_token.is_none();
+                let files: Vec<DatasetFile> = page
+                    .files
+                    .into_iter()
+                    .filter(|f| options.matches_filename(&f.filename))
+                    .collect();
+
+                Ok(Some((files, (next_token, done))))
+            }
+        });
+
+        pages
+            .map_ok(|files| stream::iter(files.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
     /// This function returns the download URL for the given file
-    pub async fn get_download_url(&self, filename: &str) -> Result<UrlResponse, ApiError> {
-        let url = self.get_file_download_url(filename);
-        let response = self
-            .create_get_request(url, None)
-            .send()
+    pub async fn get_download_url(
+        &self,
+        dataset: &DatasetConfig,
+        filename: &str,
+    ) -> Result<UrlResponse, ApiError> {
+        let url = self.get_file_download_url(dataset, filename);
+        self.request_download_url(url).await
+    }
+
+    /// Returns the download URL for the file of a notification. The URL of the notification
+    /// is only requested when it points to this API so the API key is never sent to another
+    /// host, otherwise the download URL is requested using the dataset and filename.
+    pub async fn get_notification_download_url(
+        &self,
+        data: &NotificationData,
+    ) -> Result<UrlResponse, ApiError> {
+        if data.url.starts_with(&format!("{}/", self.base_url)) {
+            return self.request_download_url(data.url.clone()).await;
+        }
+
+        self.get_download_url(&data.dataset_config(), &data.filename)
             .await
-            .map_err(|e| ApiError::FetchError(e.to_string()))?;
+    }
+
+    async fn request_download_url(&self, url: String) -> Result<UrlResponse, ApiError> {
+        let response = self
+            .send_with_retry(self.create_get_request(url, None))
+            .await?;
+        let response = check_status(response).await?;
 
         let data: UrlResponse = response
             .json()
@@ -101,48 +383,220 @@ impl OpenDataAPI {
 
     pub async fn get_latest_download_url(
         &self,
-    ) -> Result<(models::response::File, UrlResponse), ApiError> {
-        let latest_files = self.get_latest_files(1).await?;
+        dataset: &DatasetConfig,
+    ) -> Result<(DatasetFile, UrlResponse), ApiError> {
+        let latest_files = self.get_latest_files(dataset, 1).await?;
         if latest_files.files.len() != 1 {
             return Err(ApiError::FetchError("No files found".to_string()));
         }
 
         let file = latest_files.files[0].clone();
-        let response = self.get_download_url(&file.filename).await?;
+        let response = self.get_download_url(dataset, &file.filename).await?;
 
         Ok((file, response))
     }
 
+    /// Download the latest file of the dataset to the output path, stale partial files
+    /// of earlier interrupted downloads in the output path are removed.
+    /// Returns the file and the path of the downloaded file.
     pub async fn download_latest_file(
         &self,
+        dataset: &DatasetConfig,
         output_path: &str,
         filename: Option<String>,
         overwrite: Option<bool>,
-    ) -> Result<(models::response::File, String), ApiError> {
-        let (file, response) = self.get_latest_download_url().await?;
+    ) -> Result<(DatasetFile, String), ApiError> {
+        clean_stale_partial_files(output_path).await?;
+
+        let (file, response) = self.get_latest_download_url(dataset).await?;
         let filename: String = filename.unwrap_or(file.filename.clone());
-        let output_filepath = Path::new(output_path)
-            .join(filename.clone())
-            .to_str()
-            .unwrap()
-            .to_string();
+        let output_filepath = output_filepath(output_path, &filename)?;
 
+        let expected_size = response.size_in_bytes().or(Some(file.size));
         self.download_file(
             &response.temporary_download_url,
             &output_filepath,
             overwrite,
+            expected_size,
         )
         .await?;
 
         Ok((file, output_filepath))
     }
 
-    /// This function downloads a file from the given URL and saves it to the given output_filepath
+    /// Returns the file valid at the given timestamp, this is the file with the latest
+    /// timestamp in its filename which is not after the requested timestamp. For example
+    /// requesting 2024-12-22T20:57:00Z from the nowcast dataset returns the 20:55 run.
+    ///
+    /// # Errors
+    /// - `ApiError::FileNotFound`: If no file is available for the requested timestamp.
+    pub async fn get_file_at(
+        &self,
+        dataset: &DatasetConfig,
+        timestamp: DateTime<Utc>,
+    ) -> Result<DatasetFile, ApiError> {
+        let window = Duration::hours(HISTORY_SEARCH_WINDOW_HOURS);
+        let files = self
+            .get_files_with_timestamp(dataset, timestamp - window, timestamp + window)
+            .await?;
+
+        files
+            .into_iter()
+            .filter(|(file_timestamp, _)| *file_timestamp <= timestamp)
+            .max_by_key(|(file_timestamp, _)| *file_timestamp)
+            .map(|(_, file)| file)
+            .ok_or_else(|| {
+                ApiError::FileNotFound(format!("No file found for {}", timestamp.to_rfc3339()))
+            })
+    }
+
+    /// Returns all files with a timestamp in their filename between begin and end (inclusive),
+    /// ordered by their timestamp.
+    pub async fn get_files_between(
+        &self,
+        dataset: &DatasetConfig,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DatasetFile>, ApiError> {
+        // files are created after the time they are valid for
+        let window = Duration::hours(HISTORY_SEARCH_WINDOW_HOURS);
+        let mut files: Vec<(DateTime<Utc>, DatasetFile)> = self
+            .get_files_with_timestamp(dataset, begin, end + window)
+            .await?
+            .into_iter()
+            .filter(|(file_timestamp, _)| *file_timestamp >= begin && *file_timestamp <= end)
+            .collect();
+
+        files.sort_by_key(|(file_timestamp, _)| *file_timestamp);
+        Ok(files.into_iter().map(|(_, file)| file).collect())
+    }
+
+    /// List files created between begin and end together with the timestamp parsed from
+    /// their filename, files without a timestamp in their filename are skipped.
+    async fn get_files_with_timestamp(
+        &self,
+        dataset: &DatasetConfig,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, DatasetFile)>, ApiError> {
+        let options = ListFilesOptions {
+            order_by: Some(OrderBy::Created),
+            sorting: Some(Sorting::Asc),
+            begin: Some(begin),
+            end: Some(end),
+            ..Default::default()
+        };
+
+        let files: Vec<DatasetFile> = self.list_all_files(dataset, options).try_collect().await?;
+        Ok(files
+            .into_iter()
+            .filter_map(|file| timestamp_from_filename(&file.filename).map(|t| (t.and_utc(), file)))
+            .collect())
+    }
+
+    /// Download the file valid at the given timestamp to the output path.
+    /// Returns the file and the path of the downloaded file.
+    pub async fn download_file_at(
+        &self,
+        dataset: &DatasetConfig,
+        output_path: &str,
+        timestamp: DateTime<Utc>,
+        overwrite: Option<bool>,
+    ) -> Result<(DatasetFile, String), ApiError> {
+        let file = self.get_file_at(dataset, timestamp).await?;
+        let output_filepath = self
+            .download_dataset_file(dataset, &file, output_path, overwrite)
+            .await?;
+
+        Ok((file, output_filepath))
+    }
+
+    /// Download all files with a timestamp between begin and end (inclusive) to the output path.
+    /// At most `concurrency` files are downloaded at the same time. Returns the files and the
+    /// paths of the downloaded files ordered by timestamp.
+    pub async fn download_files_between(
+        &self,
+        dataset: &DatasetConfig,
+        output_path: &str,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+        concurrency: usize,
+        overwrite: Option<bool>,
+    ) -> Result<Vec<(DatasetFile, String)>, ApiError> {
+        let files = self.get_files_between(dataset, begin, end).await?;
+        self.download_dataset_files(dataset, files, output_path, concurrency, overwrite)
+            .await
+    }
+
+    /// Download the given dataset files with bounded concurrency, results are returned
+    /// in the same order as the given files.
+    pub async fn download_dataset_files(
+        &self,
+        dataset: &DatasetConfig,
+        files: Vec<DatasetFile>,
+        output_path: &str,
+        concurrency: usize,
+        overwrite: Option<bool>,
+    ) -> Result<Vec<(DatasetFile, String)>, ApiError> {
+        clean_stale_partial_files(output_path).await?;
+
+        stream::iter(files)
+            .map(|file| async move {
+                let output_filepath = self
+                    .download_dataset_file(dataset, &file, output_path, overwrite)
+                    .await?;
+                Ok((file, output_filepath))
+            })
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    /// Request a download url for the dataset file and download it to the output path.
+    pub(crate) async fn download_dataset_file(
+        &self,
+        dataset: &DatasetConfig,
+        file: &DatasetFile,
+        output_path: &str,
+        overwrite: Option<bool>,
+    ) -> Result<String, ApiError> {
+        let output_filepath = output_filepath(output_path, &file.filename)?;
+        let response = self.get_download_url(dataset, &file.filename).await?;
+
+        let expected_size = response.size_in_bytes().or(Some(file.size));
+        self.download_file(
+            &response.temporary_download_url,
+            &output_filepath,
+            overwrite,
+            expected_size,
+        )
+        .await?;
+
+        Ok(output_filepath)
+    }
+
+    /// Downloads a file from the given URL and saves it to the given output_filepath.
+    ///
+    /// The response is streamed to a temporary sibling file (`<filename>.part`) which is
+    /// renamed to the output filepath once it is complete and verified, readers will never
+    /// see a partially written file. The progress handler (if set) is called after every chunk.
+    ///
+    /// When `expected_size` is given an existing temporary file is treated as an interrupted
+    /// download and resumed using a HTTP Range request, a temporary file which already has the
+    /// expected size is moved into place without downloading it again. Temporary files which
+    /// have not been written to for a while are considered stale and removed. An existing
+    /// output file is kept when its size matches the expected size, unless `overwrite` is set.
+    ///
+    /// # Errors
+    /// - `ApiError::FetchError`: If the request fails or the server returns an error status.
+    /// - `ApiError::SaveFileError`: If the file can not be written.
+    /// - `ApiError::VerificationError`: If the downloaded size does not match the expected size.
     pub async fn download_file(
         &self,
         url: &str,
         output_filepath: &str,
         overwrite: Option<bool>,
+        expected_size: Option<u64>,
     ) -> Result<(), ApiError> {
         // Ensure the parent directory exists
         let output_filepath = Path::new(&output_filepath);
@@ -152,49 +606,917 @@ impl OpenDataAPI {
                 .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
         }
 
-        // Skip download if file already exists and overwrite is false
-        if output_filepath.exists() && overwrite.unwrap_or(false) {
-            fs::remove_file(output_filepath)
-                .await
-                .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
-        } else if output_filepath.exists() {
-            return Ok(());
+        // Skip download if the file already exists and is complete, if we can not
+        // check the size the existing file is assumed to be complete
+        if !overwrite.unwrap_or(false) {
+            if let Ok(metadata) = fs::metadata(output_filepath).await {
+                if expected_size.is_none() || expected_size == Some(metadata.len()) {
+                    return Ok(());
+                }
+            }
+        }
+
+        let partial_filepath = partial_filepath(output_filepath);
+        let mut offset = match fs::metadata(&partial_filepath).await {
+            Ok(metadata) if is_stale(&metadata) => {
+                remove_file(&partial_filepath).await?;
+                0
+            }
+            // Interrupted download, resume from the current size
+            Ok(metadata) => match expected_size {
+                Some(expected) if metadata.len() < expected => metadata.len(),
+                // Download finished but the file was not moved into place
+                Some(expected) if metadata.len() == expected => {
+                    return complete_download(&partial_filepath, output_filepath, expected_size)
+                        .await;
+                }
+                _ => {
+                    remove_file(&partial_filepath).await?;
+                    0
+                }
+            },
+            Err(_) => 0,
+        };
+
+        let mut request = self.client.get(url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
         }
 
-        let client = Client::new();
-        let response = client
-            .get(url)
-            .send()
+        let response = self.send_with_retry(request).await?;
+
+        let status = response.status();
+        if status == StatusCode::RANGE_NOT_SATISFIABLE && offset > 0 {
+            // nothing left to download, the size check decides if the file is valid
+            return complete_download(&partial_filepath, output_filepath, expected_size).await;
+        }
+
+        let mut response = check_status(response).await?;
+
+        // The server ignored the range request, download the complete file again
+        if offset > 0 && status != StatusCode::PARTIAL_CONTENT {
+            offset = 0;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(&partial_filepath)
             .await
-            .map_err(|e| ApiError::FetchError(e.to_string()))?;
+            .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
+        let mut writer = BufWriter::new(file);
+
+        let total_bytes = expected_size.or(response.content_length().map(|l| l + offset));
+        let mut downloaded_bytes = offset;
 
-        if response.status().is_success() {
-            let mut file = File::create(output_filepath)
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ApiError::FetchError(e.to_string()))?
+        {
+            writer
+                .write_all(&chunk)
                 .await
                 .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
 
-            let content = response
-                .bytes()
-                .await
-                .map_err(|e| ApiError::FetchError(e.to_string()))?;
+            downloaded_bytes += chunk.len() as u64;
+            if let Some(handler) = &self.progress_handler {
+                handler(DownloadProgress {
+                    filepath: output_filepath.to_string_lossy().to_string(),
+                    downloaded_bytes,
+                    total_bytes,
+                });
+            }
+        }
 
-            file.write_all(&content)
-                .await
-                .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
+        // Explicitly flush the buffer to ensure all data is written
+        // not doing this will result in errors while calling download and
+        // dataset load functions multiple times in a row
+        writer
+            .flush()
+            .await
+            .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
+
+        complete_download(&partial_filepath, output_filepath, expected_size).await
+    }
+}
+
+/// Client with the default timeouts and user agent, a client without these settings is used
+/// in the unlikely case it can not be created.
+fn default_client() -> Client {
+    Client::builder()
+        .connect_timeout(DEFAULT_CONNECT_TIMEOUT)
+        .read_timeout(DEFAULT_READ_TIMEOUT)
+        .user_agent(DEFAULT_USER_AGENT)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Builder for `OpenDataAPI`, see `OpenDataAPI::builder`.
+///
+/// Either a shared `reqwest::Client` is supplied or a client is created using the
+/// configured proxy, timeouts and user agent.
+#[derive(Default)]
+pub struct OpenDataAPIBuilder {
+    api_key: Option<String>,
+    base_url: Option<String>,
+    client: Option<Client>,
+    proxy: Option<Proxy>,
+    connect_timeout: Option<std::time::Duration>,
+    read_timeout: Option<std::time::Duration>,
+    user_agent: Option<String>,
+    retry_policy: Option<BackoffPolicy>,
+    progress_handler: Option<DownloadProgressHandler>,
+}
+
+impl OpenDataAPIBuilder {
+    /// API key used to authorize the requests, required.
+    pub fn api_key(mut self, api_key: String) -> Self {
+        self.api_key = Some(api_key);
+        self
+    }
 
-            // Explicitly flush the buffer to ensure all data is written
-            // not doing this will result in errors while calling download and
-            // dataset load functions multiple times in a row
-            file.flush()
+    /// Base URL of the API, default: `DEFAULT_BASE_URL`.
+    pub fn base_url(mut self, base_url: String) -> Self {
+        self.base_url = Some(base_url);
+        self
+    }
+
+    /// Use an existing client, connections are pooled with all other users of the client.
+    /// Can not be combined with a proxy, timeouts or user agent, configure those on the
+    /// client instead.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Send all requests through the given proxy.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Timeout for connecting, default: `DEFAULT_CONNECT_TIMEOUT`.
+    pub fn connect_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for a single read from the connection, default: `DEFAULT_READ_TIMEOUT`.
+    pub fn read_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// User agent sent with every request, default: `DEFAULT_USER_AGENT`.
+    pub fn user_agent(mut self, user_agent: String) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Policy used to retry requests which failed because of connection errors, rate
+    /// limiting (429) or server errors (5xx), default: `BackoffPolicy::default()`.
+    pub fn retry_policy(mut self, retry_policy: BackoffPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Handler which is called with the progress of every running download.
+    pub fn progress_handler(mut self, handler: DownloadProgressHandler) -> Self {
+        self.progress_handler = Some(handler);
+        self
+    }
+
+    /// Create the `OpenDataAPI`.
+    ///
+    /// # Errors
+    /// - `ApiError::ConfigurationError`: If no API key is set, a client is combined with
+    ///   client settings or the client can not be created.
+    pub fn build(self) -> Result<OpenDataAPI, ApiError> {
+        let api_key = self
+            .api_key
+            .ok_or_else(|| ApiError::ConfigurationError("An API key is required".to_string()))?;
+
+        let client = match self.client {
+            Some(client) => {
+                if self.proxy.is_some()
+                    || self.connect_timeout.is_some()
+                    || self.read_timeout.is_some()
+                    || self.user_agent.is_some()
+                {
+                    return Err(ApiError::ConfigurationError(
+                        "A proxy, timeouts or user agent can not be combined with a custom client"
+                            .to_string(),
+                    ));
+                }
+                client
+            }
+            None => {
+                let mut builder = Client::builder()
+                    .connect_timeout(self.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT))
+                    .read_timeout(self.read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT))
+                    .user_agent(
+                        self.user_agent
+                            .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+                    );
+                if let Some(proxy) = self.proxy {
+                    builder = builder.proxy(proxy);
+                }
+                builder
+                    .build()
+                    .map_err(|e| ApiError::ConfigurationError(e.to_string()))?
+            }
+        };
+
+        Ok(OpenDataAPI {
+            base_url: self
+                .base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            api_key,
+            client,
+            progress_handler: self.progress_handler,
+            retry_policy: self.retry_policy.unwrap_or_default(),
+            rate_limit: Arc::new(Mutex::new(None)),
+        })
+    }
+}
+
+/// Turn non successful responses into an error, the KNMI error body is parsed when possible.
+///
+/// # Errors
+/// - `ApiError::Unauthorized`: 401, the API key is invalid.
+/// - `ApiError::Forbidden`: 403, no access to the resource.
+/// - `ApiError::NotFound`: 404, the dataset, version or file does not exist.
+/// - `ApiError::RateLimitExceeded`: 429, too many requests.
+/// - `ApiError::ServerError`: 5xx.
+/// - `ApiError::UnexpectedStatus`: Any other non successful status.
+async fn check_status(response: Response) -> Result<Response, ApiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = parse_retry_after(response.headers());
+    let body = response
+        .text()
+        .await
+        .ok()
+        .and_then(|text| serde_json::from_str::<ErrorResponse>(&text).ok());
+    let error = HttpStatusError {
+        status: status.as_u16(),
+        body,
+    };
+
+    Err(match status {
+        StatusCode::UNAUTHORIZED => ApiError::Unauthorized(error),
+        StatusCode::FORBIDDEN => ApiError::Forbidden(error),
+        StatusCode::NOT_FOUND => ApiError::NotFound(error),
+        StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimitExceeded { error, retry_after },
+        s if s.is_server_error() => ApiError::ServerError(error),
+        _ => ApiError::UnexpectedStatus(error),
+    })
+}
+
+/// Parse the Retry-After header, both delay-seconds and HTTP-date are supported.
+fn parse_retry_after(headers: &HeaderMap) -> Option<std::time::Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// Parse the rate limit headers, both the `RateLimit-*` and `X-RateLimit-*` (optionally
+/// suffixed with the window such as `X-RateLimit-Remaining-Hour`) headers are supported.
+fn parse_rate_limit(headers: &HeaderMap) -> Option<RateLimit> {
+    let header_value = |name: &str| -> Option<u64> {
+        let prefixes = [
+            format!("ratelimit-{}", name),
+            format!("x-ratelimit-{}", name),
+        ];
+        headers
+            .iter()
+            .find(|(key, _)| {
+                prefixes
+                    .iter()
+                    .any(|p| key.as_str().starts_with(p.as_str()))
+            })
+            .and_then(|(_, value)| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+    };
+
+    let rate_limit = RateLimit {
+        limit: header_value("limit"),
+        remaining: header_value("remaining"),
+        reset: header_value("reset"),
+    };
+
+    if rate_limit == RateLimit::default() {
+        None
+    } else {
+        Some(rate_limit)
+    }
+}
+
+/// Extension added to files which are being downloaded.
+pub const PARTIAL_FILE_EXTENSION: &str = "part";
+
+/// Partial files which have not been modified for this amount of seconds are considered stale.
+const STALE_PARTIAL_FILE_SECONDS: u64 = 60 * 60;
+
+/// Returns the path of the file in the output path. Filenames come from the API or from
+/// notifications, only a plain file name is accepted so the file can not be written
+/// outside the output path.
+///
+/// # Errors
+/// - `ApiError::InvalidFilename`: If the filename is empty, absolute or contains a directory.
+/// - `ApiError::SaveFileError`: If the path is not valid UTF-8.
+pub(crate) fn output_filepath(output_path: &str, filename: &str) -> Result<String, ApiError> {
+    let mut components = Path::new(filename).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => {}
+        _ => return Err(ApiError::InvalidFilename(filename.to_string())),
+    }
+
+    Path::new(output_path)
+        .join(filename)
+        .to_str()
+        .map(|path| path.to_string())
+        .ok_or_else(|| {
+            ApiError::SaveFileError(format!(
+                "Path of {} in {} is not valid UTF-8",
+                filename, output_path
+            ))
+        })
+}
+
+/// Returns the path of the temporary file used while downloading the given file.
+pub fn partial_filepath(filepath: &Path) -> PathBuf {
+    let mut filename = filepath.file_name().unwrap_or_default().to_os_string();
+    filename.push(format!(".{}", PARTIAL_FILE_EXTENSION));
+    filepath.with_file_name(filename)
+}
+
+/// Check if a partial file has not been written to for a while
+fn is_stale(metadata: &Metadata) -> bool {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .map(|elapsed| elapsed.as_secs() > STALE_PARTIAL_FILE_SECONDS)
+        .unwrap_or(false)
+}
+
+/// Remove stale partial files left behind by crashed or interrupted downloads in the given
+/// directory. Returns the paths of the removed files.
+pub async fn clean_stale_partial_files(dir: &str) -> Result<Vec<String>, ApiError> {
+    let mut removed = Vec::new();
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(removed),
+        Err(e) => return Err(ApiError::SaveFileError(e.to_string())),
+    };
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| ApiError::SaveFileError(e.to_string()))?
+    {
+        let path = entry.path();
+        let is_partial = path.extension().and_then(|e| e.to_str()) == Some(PARTIAL_FILE_EXTENSION);
+        if !is_partial {
+            continue;
+        }
+
+        if let Ok(metadata) = entry.metadata().await {
+            if metadata.is_file() && is_stale(&metadata) {
+                remove_file(&path).await?;
+                removed.push(path.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
+async fn remove_file(path: &Path) -> Result<(), ApiError> {
+    fs::remove_file(path)
+        .await
+        .map_err(|e| ApiError::SaveFileError(e.to_string()))
+}
+
+/// Verify the partial file and move it into place, a partial file which fails
+/// verification is removed.
+async fn complete_download(
+    partial_filepath: &Path,
+    output_filepath: &Path,
+    expected_size: Option<u64>,
+) -> Result<(), ApiError> {
+    if let Err(e) = verify_file_size(partial_filepath, expected_size).await {
+        remove_file(partial_filepath).await?;
+        return Err(e);
+    }
+
+    fs::rename(partial_filepath, output_filepath)
+        .await
+        .map_err(|e| ApiError::SaveFileError(e.to_string()))
+}
+
+/// Check if the size of the file on disk matches the expected size.
+async fn verify_file_size(path: &Path, expected_size: Option<u64>) -> Result<(), ApiError> {
+    let Some(expected_size) = expected_size else {
+        return Ok(());
+    };
+
+    let size = fs::metadata(path)
+        .await
+        .map_err(|e| ApiError::SaveFileError(e.to_string()))?
+        .len();
+
+    if size != expected_size {
+        return Err(ApiError::VerificationError(format!(
+            "{} has a size of {} bytes, expected {} bytes",
+            path.display(),
+            size,
+            expected_size
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    fn files_page(filenames: &[&str], next_page_token: Option<&str>) -> String {
+        let files: Vec<serde_json::Value> = filenames
+            .iter()
+            .map(|f| {
+                serde_json::json!({
+                    "filename": f,
+                    "size": 100,
+                    "created": "2024-12-22T21:00:00+00:00",
+                    "lastModified": "2024-12-22T21:00:00+00:00"
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "isTruncated": next_page_token.is_some(),
+            "resultCount": files.len(),
+            "files": files,
+            "maxResults": 2,
+            "startAfterFilename": "",
+            "nextPageToken": next_page_token
+        })
+        .to_string()
+    }
+
+    fn radar_forecast() -> DatasetConfig {
+        DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string())
+    }
+
+    fn test_api(server: &mockito::Server) -> OpenDataAPI {
+        OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_list_all_files_pagination() {
+        let mut server = mockito::Server::new_async().await;
+        let path = "/datasets/radar_forecast/versions/2.0/files";
+
+        let page_1 = server
+            .mock("GET", path)
+            .match_query(Matcher::Regex("^maxKeys=2$".into()))
+            .with_body(files_page(&["A_1.h5", "B_1.h5"], Some("page2")))
+            .create_async()
+            .await;
+        let page_2 = server
+            .mock("GET", path)
+            .match_query(Matcher::Regex("^maxKeys=2&nextPageToken=page2$".into()))
+            .with_body(files_page(&["A_2.h5"], None))
+            .create_async()
+            .await;
+
+        let dataset = radar_forecast();
+        let oda = test_api(&server);
+
+        let options = ListFilesOptions {
+            max_keys: Some(2),
+            filename_prefix: Some("A_".to_string()),
+            ..Default::default()
+        };
+        let files: Vec<DatasetFile> = oda
+            .list_all_files(&dataset, options)
+            .try_collect()
+            .await
+            .unwrap();
+        let filenames: Vec<&str> = files.iter().map(|f| f.filename.as_str()).collect();
+
+        assert_eq!(filenames, vec!["A_1.h5", "A_2.h5"]);
+        page_1.assert_async().await;
+        page_2.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_file_at() {
+        let mut server = mockito::Server::new_async().await;
+        let files = server
+            .mock("GET", "/datasets/radar_forecast/versions/2.0/files")
+            .match_query(Matcher::Any)
+            .with_body(files_page(
+                &[
+                    "RAD_NL25_RAC_FM_202412222050.h5",
+                    "RAD_NL25_RAC_FM_202412222055.h5",
+                    "RAD_NL25_RAC_FM_202412222100.h5",
+                ],
+                None,
+            ))
+            .expect(2)
+            .create_async()
+            .await;
+
+        let dataset = radar_forecast();
+        let oda = test_api(&server);
+
+        let timestamp = "2024-12-22T20:57:00Z".parse::<DateTime<Utc>>().unwrap();
+        let file = oda.get_file_at(&dataset, timestamp).await.unwrap();
+        assert_eq!(file.filename, "RAD_NL25_RAC_FM_202412222055.h5");
+
+        let timestamp = "2024-12-22T20:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert!(matches!(
+            oda.get_file_at(&dataset, timestamp).await.unwrap_err(),
+            ApiError::FileNotFound(_)
+        ));
+
+        files.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_download_file_resume_and_verify() {
+        let mut server = mockito::Server::new_async().await;
+        let dir = tempfile::tempdir().unwrap();
+        let filepath = dir.path().join("file.h5");
+        let filepath = filepath.to_str().unwrap();
+        let url = format!("{}/file.h5", server.url());
+
+        // partial download of 5 bytes, the remaining bytes are requested using a range request
+        std::fs::write(partial_filepath(Path::new(filepath)), b"01234").unwrap();
+        let partial = server
+            .mock("GET", "/file.h5")
+            .match_header("range", "bytes=5-")
+            .with_status(206)
+            .with_body("56789")
+            .create_async()
+            .await;
+
+        let progress = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let progress_clone = progress.clone();
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .progress_handler(Arc::new(move |p: DownloadProgress| {
+                progress_clone.lock().unwrap().push(p.downloaded_bytes)
+            }))
+            .build()
+            .unwrap();
+
+        oda.download_file(&url, filepath, None, Some(10))
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(filepath).unwrap(), b"0123456789");
+        assert!(!partial_filepath(Path::new(filepath)).exists());
+        assert_eq!(progress.lock().unwrap().last(), Some(&10));
+        partial.assert_async().await;
+
+        // complete file is not downloaded again
+        oda.download_file(&url, filepath, None, Some(10))
+            .await
+            .unwrap();
+
+        // size mismatch after download
+        let full = server
+            .mock("GET", "/file.h5")
+            .with_body("0123")
+            .create_async()
+            .await;
+        assert!(matches!(
+            oda.download_file(&url, filepath, Some(true), Some(10))
                 .await
-                .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
+                .unwrap_err(),
+            ApiError::VerificationError(_)
+        ));
+        full.assert_async().await;
+
+        // the complete file is kept and the invalid partial file is removed
+        assert_eq!(std::fs::read(filepath).unwrap(), b"0123456789");
+        assert!(!partial_filepath(Path::new(filepath)).exists());
+    }
 
-            Ok(())
-        } else {
-            Err(ApiError::FetchError(format!(
-                "Failed to download file: {}",
-                response.status()
-            )))
+    #[tokio::test]
+    async fn test_download_file_complete_partial_file() {
+        let server = mockito::Server::new_async().await;
+        let dir = tempfile::tempdir().unwrap();
+        let filepath = dir.path().join("file.h5");
+        let filepath = filepath.to_str().unwrap();
+        let url = format!("{}/file.h5", server.url());
+
+        // a partial file of the expected size is moved into place without a request
+        std::fs::write(partial_filepath(Path::new(filepath)), b"0123456789").unwrap();
+        let oda = OpenDataAPI::new("key".to_string());
+        oda.download_file(&url, filepath, None, Some(10))
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(filepath).unwrap(), b"0123456789");
+        assert!(!partial_filepath(Path::new(filepath)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_clean_stale_partial_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let stale = dir.path().join("stale.h5.part");
+        let fresh = dir.path().join("fresh.h5.part");
+        let complete = dir.path().join("complete.h5");
+        for path in [&stale, &fresh, &complete] {
+            std::fs::write(path, b"0123").unwrap();
         }
+
+        let two_hours_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(7200);
+        for path in [&stale, &complete] {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(two_hours_ago)
+                .unwrap();
+        }
+
+        let removed = clean_stale_partial_files(dir.path().to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(removed, vec![stale.to_string_lossy().to_string()]);
+        assert!(fresh.exists());
+        assert!(complete.exists());
+    }
+
+    #[test]
+    fn test_partial_filepath() {
+        assert_eq!(
+            partial_filepath(Path::new("./output/RAD_NL25_RAC_FM_202412222055.h5")),
+            PathBuf::from("./output/RAD_NL25_RAC_FM_202412222055.h5.part")
+        );
+    }
+
+    #[test]
+    fn test_output_filepath() {
+        assert_eq!(
+            output_filepath("./output", "RAD_NL25_RAC_FM_202412222055.h5").unwrap(),
+            "./output/RAD_NL25_RAC_FM_202412222055.h5"
+        );
+
+        for filename in ["", ".", "..", "../../x.h5", "/etc/x.h5", "dir/x.h5"] {
+            assert!(matches!(
+                output_filepath("./output", filename).unwrap_err(),
+                ApiError::InvalidFilename(_)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_rate_limited() {
+        let mut server = mockito::Server::new_async().await;
+        let path = "/datasets/radar_forecast/versions/2.0/files";
+
+        let rate_limited = server
+            .mock("GET", path)
+            .match_query(Matcher::Any)
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("GET", path)
+            .match_query(Matcher::Any)
+            .with_header("x-ratelimit-limit-hour", "1000")
+            .with_header("x-ratelimit-remaining-hour", "998")
+            .with_body(files_page(&["A_1.h5"], None))
+            .create_async()
+            .await;
+
+        let dataset = radar_forecast();
+        let oda = test_api(&server);
+        assert!(oda.rate_limit().is_none());
+
+        let files = oda.get_latest_files(&dataset, 1).await.unwrap();
+        assert_eq!(files.files.len(), 1);
+        assert_eq!(
+            oda.rate_limit(),
+            Some(RateLimit {
+                limit: Some(1000),
+                remaining: Some(998),
+                reset: None
+            })
+        );
+
+        rate_limited.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_retry_exhausted() {
+        let mut server = mockito::Server::new_async().await;
+        let rate_limited = server
+            .mock("GET", "/datasets/radar_forecast/versions/2.0/files")
+            .match_query(Matcher::Any)
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .with_body(r#"{"error": "Too Many Requests", "message": "Rate limit exceeded"}"#)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let dataset = radar_forecast();
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .retry_policy(BackoffPolicy {
+                max_retries: Some(2),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let error = oda.get_latest_files(&dataset, 1).await.unwrap_err();
+        assert_eq!(error.status(), Some(429));
+        match error {
+            ApiError::RateLimitExceeded { error, retry_after } => {
+                assert_eq!(
+                    error.body.unwrap().description(),
+                    Some("Rate limit exceeded")
+                );
+                assert_eq!(retry_after, Some(std::time::Duration::ZERO));
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+        rate_limited.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_http_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let unauthorized = server
+            .mock("GET", "/datasets/radar_forecast/versions/2.0/files")
+            .match_query(Matcher::Any)
+            .with_status(401)
+            .with_body(r#"{"error": "Unauthorized", "message": "Invalid API key"}"#)
+            .create_async()
+            .await;
+        let not_found = server
+            .mock(
+                "GET",
+                "/datasets/radar_forecast/versions/2.0/files/unknown.h5/url",
+            )
+            .with_status(404)
+            .with_body("not json")
+            .create_async()
+            .await;
+
+        let dataset = radar_forecast();
+        let oda = test_api(&server);
+
+        let error = oda.get_latest_files(&dataset, 1).await.unwrap_err();
+        assert_eq!(error.status(), Some(401));
+        match error {
+            ApiError::Unauthorized(e) => {
+                assert_eq!(e.body.unwrap().description(), Some("Invalid API key"))
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let error = oda
+            .get_download_url(&dataset, "unknown.h5")
+            .await
+            .unwrap_err();
+        match error {
+            ApiError::NotFound(e) => {
+                assert_eq!(e.status, 404);
+                assert!(e.body.is_none());
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        unauthorized.assert_async().await;
+        not_found.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_builder() {
+        assert!(matches!(
+            OpenDataAPI::builder().build().err(),
+            Some(ApiError::ConfigurationError(_))
+        ));
+        assert!(matches!(
+            OpenDataAPI::builder()
+                .api_key("key".to_string())
+                .client(Client::new())
+                .user_agent("test".to_string())
+                .build()
+                .err(),
+            Some(ApiError::ConfigurationError(_))
+        ));
+
+        let mut server = mockito::Server::new_async().await;
+        let files = server
+            .mock("GET", "/datasets/radar_forecast/versions/2.0/files")
+            .match_query(Matcher::Any)
+            .match_header("user-agent", "test-agent/1.0")
+            .match_header("authorization", "key")
+            .with_body(files_page(&["A_1.h5"], None))
+            .create_async()
+            .await;
+        let observations = server
+            .mock(
+                "GET",
+                "/datasets/Actuele10mindataKNMIstations/versions/2/files",
+            )
+            .match_query(Matcher::Any)
+            .match_header("user-agent", "test-agent/1.0")
+            .with_body(files_page(&["B_1.nc"], None))
+            .create_async()
+            .await;
+
+        // a single instance is used for multiple datasets
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .user_agent("test-agent/1.0".to_string())
+            .connect_timeout(std::time::Duration::from_secs(1))
+            .build()
+            .unwrap();
+        let observations_config =
+            DatasetConfig::new("Actuele10mindataKNMIstations".to_string(), "2".to_string());
+
+        let radar = oda.get_latest_files(&radar_forecast(), 1).await.unwrap();
+        let stations = oda.get_latest_files(&observations_config, 1).await.unwrap();
+        assert_eq!(radar.files[0].filename, "A_1.h5");
+        assert_eq!(stations.files[0].filename, "B_1.nc");
+
+        files.assert_async().await;
+        observations.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_dataset_metadata() {
+        let mut server = mockito::Server::new_async().await;
+        let dataset_mock = server
+            .mock("GET", "/datasets/radar_forecast")
+            .with_body(r#"{"datasetName": "radar_forecast", "versions": ["2.0"]}"#)
+            .create_async()
+            .await;
+        let version_mock = server
+            .mock("GET", "/datasets/radar_forecast/versions/2.0")
+            .with_body(
+                r#"{"datasetName": "radar_forecast", "versionId": "2.0", "fileCount": 288, "updateFrequency": "PT5M"}"#,
+            )
+            .create_async()
+            .await;
+        let unknown_mock = server
+            .mock("GET", "/datasets/radar_forecast/versions/9.9")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let dataset = radar_forecast();
+        let oda = test_api(&server);
+
+        let metadata = oda.get_dataset_metadata("radar_forecast").await.unwrap();
+        assert_eq!(metadata.versions, vec!["2.0"]);
+
+        let version = oda.validate_dataset(&dataset).await.unwrap();
+        assert_eq!(version.file_count, Some(288));
+        assert_eq!(
+            version.update_interval(),
+            Some(std::time::Duration::from_secs(300))
+        );
+
+        let unknown = DatasetConfig::new("radar_forecast".to_string(), "9.9".to_string());
+        assert!(matches!(
+            oda.validate_dataset(&unknown).await.unwrap_err(),
+            ApiError::ConfigurationError(_)
+        ));
+
+        dataset_mock.assert_async().await;
+        version_mock.assert_async().await;
+        unknown_mock.assert_async().await;
     }
 }
@@ -0,0 +1,382 @@
+use super::api::{OpenDataAPI, PARTIAL_FILE_EXTENSION};
+use super::models::config::DatasetConfig;
+use crate::errors::ApiError;
+use crate::utils::timestamp_from_filename;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::fs;
+
+/// Retention policy of a dataset cache, files exceeding any of the limits are removed.
+/// The latest file of a dataset version is always kept.
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    /// max number of files to keep per dataset version
+    pub max_files: Option<usize>,
+    /// max age of a file based on the timestamp in its filename
+    pub max_age: Option<Duration>,
+}
+
+impl RetentionPolicy {
+    /// Keep the last `max_files` files of a dataset version.
+    pub fn keep_last(max_files: usize) -> Self {
+        RetentionPolicy {
+            max_files: Some(max_files),
+            max_age: None,
+        }
+    }
+
+    /// Keep the files of the last `max_age`.
+    pub fn keep_for(max_age: Duration) -> Self {
+        RetentionPolicy {
+            max_files: None,
+            max_age: Some(max_age),
+        }
+    }
+}
+
+/// A file stored in the cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedFile {
+    pub filename: String,
+    pub path: String,
+    /// Timestamp parsed from the filename, the modification time when the filename
+    /// has no timestamp
+    pub timestamp: DateTime<Utc>,
+    /// Last time the file was downloaded or confirmed to be the latest file
+    pub modified: SystemTime,
+}
+
+/// Local cache of downloaded dataset files, files are stored in
+/// `<root>/<dataset name>/<version>/<filename>`.
+#[derive(Debug, Clone)]
+pub struct DatasetCache {
+    root: PathBuf,
+    retention: RetentionPolicy,
+}
+
+impl DatasetCache {
+    pub fn new(root: impl Into<PathBuf>, retention: RetentionPolicy) -> Self {
+        DatasetCache {
+            root: root.into(),
+            retention,
+        }
+    }
+
+    /// Directory the files of the dataset version are stored in.
+    pub fn dataset_dir(&self, dataset: &DatasetConfig) -> PathBuf {
+        self.root.join(&dataset.dataset_name).join(&dataset.version)
+    }
+
+    /// Path of a file of the dataset version in the cache, the file does not have to exist.
+    pub fn file_path(&self, dataset: &DatasetConfig, filename: &str) -> PathBuf {
+        self.dataset_dir(dataset).join(filename)
+    }
+
+    /// Check if the file of the dataset version is in the cache.
+    pub fn contains(&self, dataset: &DatasetConfig, filename: &str) -> bool {
+        self.file_path(dataset, filename).is_file()
+    }
+
+    /// Returns the cached files of the dataset version ordered by timestamp, oldest first.
+    /// Partial downloads are skipped.
+    pub async fn list(&self, dataset: &DatasetConfig) -> Result<Vec<CachedFile>, ApiError> {
+        let mut files = Vec::new();
+        let mut entries = match fs::read_dir(self.dataset_dir(dataset)).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+            Err(e) => return Err(ApiError::CacheError(e.to_string())),
+        };
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| ApiError::CacheError(e.to_string()))?
+        {
+            let path = entry.path();
+            let is_partial =
+                path.extension().and_then(|e| e.to_str()) == Some(PARTIAL_FILE_EXTENSION);
+            let metadata = match entry.metadata().await {
+                Ok(metadata) if metadata.is_file() && !is_partial => metadata,
+                _ => continue,
+            };
+
+            let filename = entry.file_name().to_string_lossy().to_string();
+            let modified = metadata
+                .modified()
+                .map_err(|e| ApiError::CacheError(e.to_string()))?;
+            let timestamp = timestamp_from_filename(&filename)
+                .map(|t| t.and_utc())
+                .unwrap_or_else(|| DateTime::<Utc>::from(modified));
+
+            files.push(CachedFile {
+                filename,
+                path: path.to_string_lossy().to_string(),
+                timestamp,
+                modified,
+            });
+        }
+
+        files.sort_by(|a, b| {
+            a.timestamp
+                .cmp(&b.timestamp)
+                .then_with(|| a.filename.cmp(&b.filename))
+        });
+        Ok(files)
+    }
+
+    /// Returns the latest cached file of the dataset version.
+    pub async fn latest(&self, dataset: &DatasetConfig) -> Result<Option<CachedFile>, ApiError> {
+        Ok(self.list(dataset).await?.pop())
+    }
+
+    /// Returns the latest cached file if it was downloaded or confirmed to be the latest
+    /// file less than `max_age` ago.
+    pub async fn fresh(
+        &self,
+        dataset: &DatasetConfig,
+        max_age: Duration,
+    ) -> Result<Option<CachedFile>, ApiError> {
+        Ok(self.latest(dataset).await?.filter(|file| {
+            file.modified
+                .elapsed()
+                .map(|elapsed| elapsed < max_age)
+                .unwrap_or(true)
+        }))
+    }
+
+    /// Remove the files of the dataset version which exceed the retention policy,
+    /// the latest file is always kept. Returns the paths of the removed files.
+    pub async fn prune(&self, dataset: &DatasetConfig) -> Result<Vec<String>, ApiError> {
+        let mut files = self.list(dataset).await?;
+        // the latest file is never removed
+        files.pop();
+
+        let mut expired = Vec::new();
+        if let Some(max_files) = self.retention.max_files {
+            // the latest file is already excluded from the list
+            let remove_count = (files.len() + 1).saturating_sub(max_files.max(1));
+            expired.extend(files.drain(..remove_count));
+        }
+
+        if let Some(max_age) = self.retention.max_age {
+            let max_age = chrono::Duration::from_std(max_age)
+                .map_err(|e| ApiError::ConfigurationError(e.to_string()))?;
+            let oldest = Utc::now() - max_age;
+            let (old, _): (Vec<CachedFile>, Vec<CachedFile>) =
+                files.into_iter().partition(|file| file.timestamp < oldest);
+            expired.extend(old);
+        }
+
+        let mut removed = Vec::new();
+        for file in expired {
+            fs::remove_file(&file.path)
+                .await
+                .map_err(|e| ApiError::CacheError(e.to_string()))?;
+            removed.push(file.path);
+        }
+
+        Ok(removed)
+    }
+
+    /// Returns the path of the latest file of the dataset version. The cached file is used
+    /// when it is fresh, see `fresh`, otherwise the latest file is requested from the API and
+    /// downloaded when it is not cached yet. Files exceeding the retention policy are removed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kanemi::dataplatform::api::OpenDataAPI;
+    /// use kanemi::dataplatform::cache::{DatasetCache, RetentionPolicy};
+    /// use kanemi::dataplatform::models::config::KnownDataset;
+    ///
+    /// # async fn example() -> Result<(), kanemi::errors::ApiError> {
+    /// let oda = OpenDataAPI::new("api-key".to_string());
+    /// let cache = DatasetCache::new("./output", RetentionPolicy::keep_last(1));
+    /// let dataset = KnownDataset::NowcastPrecipitation;
+    /// let filepath = cache
+    ///     .get_latest(&oda, &dataset.config(), dataset.update_interval())
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_latest(
+        &self,
+        oda: &OpenDataAPI,
+        dataset: &DatasetConfig,
+        max_age: Duration,
+    ) -> Result<String, ApiError> {
+        if let Some(file) = self.fresh(dataset, max_age).await? {
+            return Ok(file.path);
+        }
+
+        let dir = self.dataset_dir(dataset);
+        fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| ApiError::CacheError(e.to_string()))?;
+
+        let (_, filepath) = oda
+            .download_latest_file(dataset, &dir.to_string_lossy(), None, Some(false))
+            .await?;
+
+        // an existing file is not downloaded again, mark it as confirmed to be the latest file
+        touch(Path::new(&filepath))?;
+        self.prune(dataset).await?;
+
+        Ok(filepath)
+    }
+}
+
+/// Set the modification time of the file to now.
+fn touch(path: &Path) -> Result<(), ApiError> {
+    std::fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()))
+        .map_err(|e| ApiError::CacheError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn radar_forecast() -> DatasetConfig {
+        DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string())
+    }
+
+    fn write_files(cache: &DatasetCache, dataset: &DatasetConfig, filenames: &[&str]) {
+        std::fs::create_dir_all(cache.dataset_dir(dataset)).unwrap();
+        for filename in filenames {
+            std::fs::write(cache.file_path(dataset, filename), b"0123").unwrap();
+        }
+    }
+
+    fn filename_at(timestamp: DateTime<Utc>) -> String {
+        format!("RAD_NL25_RAC_FM_{}.h5", timestamp.format("%Y%m%d%H%M"))
+    }
+
+    #[tokio::test]
+    async fn test_list_and_prune_keep_last() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DatasetCache::new(dir.path(), RetentionPolicy::keep_last(2));
+        let dataset = radar_forecast();
+        write_files(
+            &cache,
+            &dataset,
+            &[
+                "RAD_NL25_RAC_FM_202412222100.h5",
+                "RAD_NL25_RAC_FM_202412222050.h5",
+                "RAD_NL25_RAC_FM_202412222055.h5",
+                "RAD_NL25_RAC_FM_202412222105.h5.part",
+            ],
+        );
+
+        let files = cache.list(&dataset).await.unwrap();
+        let filenames: Vec<&str> = files.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(
+            filenames,
+            vec![
+                "RAD_NL25_RAC_FM_202412222050.h5",
+                "RAD_NL25_RAC_FM_202412222055.h5",
+                "RAD_NL25_RAC_FM_202412222100.h5",
+            ]
+        );
+
+        let removed = cache.prune(&dataset).await.unwrap();
+        assert_eq!(removed, vec![files[0].path.clone()]);
+        assert!(!cache.contains(&dataset, "RAD_NL25_RAC_FM_202412222050.h5"));
+        assert!(cache.contains(&dataset, "RAD_NL25_RAC_FM_202412222100.h5"));
+    }
+
+    #[tokio::test]
+    async fn test_prune_max_age_keeps_latest() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DatasetCache::new(
+            dir.path(),
+            RetentionPolicy::keep_for(Duration::from_secs(3600)),
+        );
+        let dataset = radar_forecast();
+        let recent = filename_at(Utc::now() - chrono::Duration::minutes(30));
+        write_files(
+            &cache,
+            &dataset,
+            &["RAD_NL25_RAC_FM_202412222050.h5", &recent],
+        );
+
+        cache.prune(&dataset).await.unwrap();
+        let files = cache.list(&dataset).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].filename, recent);
+
+        // the latest file is kept even when it is expired
+        let dataset = DatasetConfig::new("old".to_string(), "1".to_string());
+        write_files(&cache, &dataset, &["RAD_NL25_RAC_FM_202412222050.h5"]);
+        assert!(cache.prune(&dataset).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_uses_fresh_file() {
+        let mut server = mockito::Server::new_async().await;
+        let files = server
+            .mock("GET", "/datasets/radar_forecast/versions/2.0/files")
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                serde_json::json!({
+                    "isTruncated": false,
+                    "resultCount": 1,
+                    "files": [{
+                        "filename": "RAD_NL25_RAC_FM_202412222055.h5",
+                        "size": 4,
+                        "created": "2024-12-22T21:00:00+00:00",
+                        "lastModified": "2024-12-22T21:00:00+00:00"
+                    }],
+                    "maxResults": 1
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let url = server
+            .mock(
+                "GET",
+                "/datasets/radar_forecast/versions/2.0/files/RAD_NL25_RAC_FM_202412222055.h5/url",
+            )
+            .with_body(
+                serde_json::json!({
+                    "contentType": "application/x-hdf5",
+                    "lastModified": "2024-12-22T21:00:00+00:00",
+                    "size": "4",
+                    "temporaryDownloadUrl": format!("{}/download", server.url())
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let download = server
+            .mock("GET", "/download")
+            .with_body("0123")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DatasetCache::new(dir.path(), RetentionPolicy::keep_last(1));
+        let dataset = radar_forecast();
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let max_age = Duration::from_secs(300);
+        let first = cache.get_latest(&oda, &dataset, max_age).await.unwrap();
+        let second = cache.get_latest(&oda, &dataset, max_age).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(std::fs::read(&first).unwrap(), b"0123");
+
+        files.assert_async().await;
+        url.assert_async().await;
+        download.assert_async().await;
+    }
+}
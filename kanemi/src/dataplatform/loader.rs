@@ -0,0 +1,98 @@
+use super::api::OpenDataAPI;
+use super::models::config::KnownDataset;
+use crate::errors::ApiError;
+use crate::{actual_synoptic_observations, harmonie_cy43_p1, nowcast_precipitation};
+
+/// A downloaded file opened with the kanemi dataset type of the known dataset.
+#[derive(Debug)]
+pub enum LoadedDataset {
+    NowcastPrecipitation(Box<nowcast_precipitation::dataset::Dataset>),
+    ActualSynopticObservations(actual_synoptic_observations::dataset::Dataset),
+    HarmonieCy43P1(harmonie_cy43_p1::dataset::Dataset),
+}
+
+impl LoadedDataset {
+    /// The known dataset the file belongs to.
+    pub fn known_dataset(&self) -> KnownDataset {
+        match self {
+            LoadedDataset::NowcastPrecipitation(_) => KnownDataset::NowcastPrecipitation,
+            LoadedDataset::ActualSynopticObservations(_) => {
+                KnownDataset::ActualSynopticObservations
+            }
+            LoadedDataset::HarmonieCy43P1(_) => KnownDataset::HarmonieCy43P1,
+        }
+    }
+}
+
+impl KnownDataset {
+    /// Open a downloaded file of the dataset, HARMONIE archives are extracted to a
+    /// temporary directory which is removed when the dataset is dropped.
+    ///
+    /// # Errors
+    /// - `ApiError::DatasetLoadError`: If the file can not be opened.
+    pub fn load(&self, filepath: &str) -> Result<LoadedDataset, ApiError> {
+        let error = |e: String| ApiError::DatasetLoadError(format!("{}: {}", filepath, e));
+
+        match self {
+            KnownDataset::NowcastPrecipitation => {
+                nowcast_precipitation::dataset::Dataset::new(filepath.to_string())
+                    .map(|dataset| LoadedDataset::NowcastPrecipitation(Box::new(dataset)))
+                    .map_err(|e| error(e.to_string()))
+            }
+            KnownDataset::ActualSynopticObservations => {
+                actual_synoptic_observations::dataset::Dataset::new(filepath.to_string())
+                    .map(LoadedDataset::ActualSynopticObservations)
+                    .map_err(|e| error(e.to_string()))
+            }
+            KnownDataset::HarmonieCy43P1 => {
+                harmonie_cy43_p1::dataset::Dataset::from_tar(filepath, None)
+                    .map(LoadedDataset::HarmonieCy43P1)
+                    .map_err(|e| error(e.to_string()))
+            }
+        }
+    }
+}
+
+impl OpenDataAPI {
+    /// Download the latest file of a known dataset to the output path and open it.
+    /// Returns the path of the downloaded file and the loaded dataset. The file is opened on
+    /// a blocking thread as HARMONIE archives can take a while to extract.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kanemi::dataplatform::api::OpenDataAPI;
+    /// use kanemi::dataplatform::loader::LoadedDataset;
+    /// use kanemi::dataplatform::models::config::KnownDataset;
+    ///
+    /// # async fn example() -> Result<(), kanemi::errors::ApiError> {
+    /// let oda = OpenDataAPI::new("api-key".to_string());
+    /// let (_, dataset) = oda
+    ///     .download_latest_dataset(KnownDataset::NowcastPrecipitation, "./output")
+    ///     .await?;
+    /// if let LoadedDataset::NowcastPrecipitation(dataset) = dataset {
+    ///     let forecast = dataset.get_forecast(5.0, 52.0);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// - Any error returned by `download_latest_file`.
+    /// - `ApiError::DatasetLoadError`: If the downloaded file can not be opened or loading it
+    ///   did not finish.
+    pub async fn download_latest_dataset(
+        &self,
+        dataset: KnownDataset,
+        output_path: &str,
+    ) -> Result<(String, LoadedDataset), ApiError> {
+        let (_, filepath) = self
+            .download_latest_file(&dataset.config(), output_path, None, Some(false))
+            .await?;
+        let load_filepath = filepath.clone();
+        let loaded = tokio::task::spawn_blocking(move || dataset.load(&load_filepath))
+            .await
+            .map_err(|e| ApiError::DatasetLoadError(format!("{}: {}", filepath, e)))??;
+
+        Ok((filepath, loaded))
+    }
+}
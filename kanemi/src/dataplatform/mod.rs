@@ -1,3 +1,9 @@
 pub mod api;
+pub mod cache;
+pub mod loader;
 pub mod models;
 pub mod notification;
+pub mod pipeline;
+pub mod poller;
+pub mod store;
+pub mod sync;
@@ -1,4 +1,24 @@
-#[derive(Debug)]
+use once_cell::sync::Lazy;
+use rand::Rng;
+use regex::Regex;
+use rumqttc::{TlsConfiguration, Transport};
+use std::time::Duration;
+
+const NOWCAST_PRECIPITATION_PATTERN: &str = r"^RAD_NL25_RAC_FM_\d{12}\.h5$";
+const ACTUAL_SYNOPTIC_OBSERVATIONS_PATTERN: &str = r"^KMDS__OPER_P___10M_OBS_L2_\d{12}\.nc$";
+const HARMONIE_CY43_P1_PATTERN: &str = r"^HARM43_V1_P1_\d{10}\.tar$";
+
+static NOWCAST_PRECIPITATION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(NOWCAST_PRECIPITATION_PATTERN).unwrap());
+static ACTUAL_SYNOPTIC_OBSERVATIONS_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(ACTUAL_SYNOPTIC_OBSERVATIONS_PATTERN).unwrap());
+static HARMONIE_CY43_P1_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(HARMONIE_CY43_P1_PATTERN).unwrap());
+
+/// Default keep alive interval of the MQTT connection.
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, PartialEq, Eq)]
 pub struct DatasetConfig {
     pub dataset_name: String,
     pub version: String,
@@ -22,23 +42,179 @@ impl DatasetConfig {
     }
 }
 
-#[derive(Debug)]
+/// KNMI datasets which can be loaded by kanemi.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KnownDataset {
+    /// Precipitation nowcast based on radar, loaded by `nowcast_precipitation::dataset::Dataset`
+    NowcastPrecipitation,
+    /// 10 minute observations of the KNMI stations, loaded by
+    /// `actual_synoptic_observations::dataset::Dataset`
+    ActualSynopticObservations,
+    /// HARMONIE-AROME Cy43 P1 forecast archive, loaded by `harmonie_cy43_p1::dataset::Dataset`
+    HarmonieCy43P1,
+}
+
+impl KnownDataset {
+    pub const ALL: [KnownDataset; 3] = [
+        KnownDataset::NowcastPrecipitation,
+        KnownDataset::ActualSynopticObservations,
+        KnownDataset::HarmonieCy43P1,
+    ];
+
+    pub fn dataset_name(&self) -> &'static str {
+        match self {
+            KnownDataset::NowcastPrecipitation => "radar_forecast",
+            KnownDataset::ActualSynopticObservations => "Actuele10mindataKNMIstations",
+            KnownDataset::HarmonieCy43P1 => "harmonie_arome_cy43_p1",
+        }
+    }
+
+    pub fn version(&self) -> &'static str {
+        match self {
+            KnownDataset::NowcastPrecipitation => "2.0",
+            KnownDataset::ActualSynopticObservations => "2",
+            KnownDataset::HarmonieCy43P1 => "1.0",
+        }
+    }
+
+    pub fn config(&self) -> DatasetConfig {
+        DatasetConfig::new(self.dataset_name().to_string(), self.version().to_string())
+    }
+
+    /// Regex the filenames of the dataset match, for example `RAD_NL25_RAC_FM_202412222055.h5`,
+    /// `KMDS__OPER_P___10M_OBS_L2_202412201930.nc` or `HARM43_V1_P1_2024122218.tar`.
+    pub fn filename_pattern(&self) -> &'static str {
+        match self {
+            KnownDataset::NowcastPrecipitation => NOWCAST_PRECIPITATION_PATTERN,
+            KnownDataset::ActualSynopticObservations => ACTUAL_SYNOPTIC_OBSERVATIONS_PATTERN,
+            KnownDataset::HarmonieCy43P1 => HARMONIE_CY43_P1_PATTERN,
+        }
+    }
+
+    /// Check if the filename belongs to the dataset.
+    pub fn matches_filename(&self, filename: &str) -> bool {
+        let regex = match self {
+            KnownDataset::NowcastPrecipitation => &NOWCAST_PRECIPITATION_REGEX,
+            KnownDataset::ActualSynopticObservations => &ACTUAL_SYNOPTIC_OBSERVATIONS_REGEX,
+            KnownDataset::HarmonieCy43P1 => &HARMONIE_CY43_P1_REGEX,
+        };
+
+        regex.is_match(filename)
+    }
+
+    /// Time between two consecutive files of the dataset.
+    pub fn update_interval(&self) -> Duration {
+        match self {
+            KnownDataset::NowcastPrecipitation => Duration::from_secs(5 * 60),
+            KnownDataset::ActualSynopticObservations => Duration::from_secs(10 * 60),
+            KnownDataset::HarmonieCy43P1 => Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// Find the known dataset for a dataset config, None if the dataset is not supported.
+    pub fn from_config(config: &DatasetConfig) -> Option<KnownDataset> {
+        KnownDataset::ALL.into_iter().find(|dataset| {
+            dataset.dataset_name() == config.dataset_name && dataset.version() == config.version
+        })
+    }
+}
+
+/// Topic the notification service subscribes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopicSubscription {
+    /// All notifications of a dataset version
+    Dataset(DatasetConfig),
+    /// Topic relative to the topic base, MQTT wildcards are allowed, for example
+    /// `radar_forecast/+/created` or `#` for all datasets
+    Topic(String),
+}
+
+impl TopicSubscription {
+    /// The full topic filter including the topic base.
+    pub fn topic(&self, topic_base: &str) -> String {
+        match self {
+            TopicSubscription::Dataset(dataset) => format!(
+                "{}/{}/{}/#",
+                topic_base, dataset.dataset_name, dataset.version
+            ),
+            TopicSubscription::Topic(topic) => {
+                format!("{}/{}", topic_base, topic.trim_start_matches('/'))
+            }
+        }
+    }
+}
+
+/// Transport used to connect to the MQTT broker.
+#[derive(Debug, Clone)]
+pub enum MqttTransport {
+    /// Plain TCP, for example to connect to a local broker
+    Tcp,
+    /// MQTT over TLS
+    Tls(TlsConfiguration),
+    /// MQTT over secure websockets, used by the KNMI notification service. The broker
+    /// has to be an url such as `wss://mqtt.dataplatform.knmi.nl`
+    Wss(TlsConfiguration),
+}
+
+impl MqttTransport {
+    /// Secure websockets using the native root certificates.
+    pub fn wss() -> Self {
+        MqttTransport::Wss(TlsConfiguration::default())
+    }
+
+    /// TLS using the native root certificates.
+    pub fn tls() -> Self {
+        MqttTransport::Tls(TlsConfiguration::default())
+    }
+
+    pub(crate) fn transport(&self) -> Transport {
+        match self {
+            MqttTransport::Tcp => Transport::Tcp,
+            MqttTransport::Tls(tls_config) => Transport::Tls(tls_config.clone()),
+            MqttTransport::Wss(tls_config) => Transport::Wss(tls_config.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct MqttConfig {
     pub api_key: String,
     pub broker: String,
     pub port: u16,
     pub topic_base: String,
-    pub dataset_config: DatasetConfig,
+    /// transport used to connect to the broker, default: secure websockets
+    pub transport: MqttTransport,
+    /// interval of the keep alive pings in whole seconds, set with `with_keep_alive`
+    keep_alive: Duration,
+    /// topics to subscribe to, all topics share a single connection
+    pub subscriptions: Vec<TopicSubscription>,
+    /// policy used to reconnect after connecting failed or the connection was lost
+    pub reconnect_policy: BackoffPolicy,
 }
 
 impl MqttConfig {
     pub fn new_default(api_key: String, dataset_config: DatasetConfig) -> Self {
+        Self::new_default_with_subscriptions(
+            api_key,
+            vec![TopicSubscription::Dataset(dataset_config)],
+        )
+    }
+
+    /// Config for the KNMI notification service subscribing to all given topics on a
+    /// single connection.
+    pub fn new_default_with_subscriptions(
+        api_key: String,
+        subscriptions: Vec<TopicSubscription>,
+    ) -> Self {
         MqttConfig {
             api_key,
-            dataset_config,
+            subscriptions,
             broker: "wss://mqtt.dataplatform.knmi.nl".to_string(),
             port: 443,
             topic_base: "dataplatform/file/v1".to_string(),
+            transport: MqttTransport::wss(),
+            keep_alive: DEFAULT_KEEP_ALIVE,
+            reconnect_policy: BackoffPolicy::reconnect(),
         }
     }
 
@@ -51,10 +227,217 @@ impl MqttConfig {
     ) -> Self {
         MqttConfig {
             api_key,
-            dataset_config,
+            subscriptions: vec![TopicSubscription::Dataset(dataset_config)],
             broker,
             port,
             topic_base,
+            transport: MqttTransport::wss(),
+            keep_alive: DEFAULT_KEEP_ALIVE,
+            reconnect_policy: BackoffPolicy::reconnect(),
         }
     }
+
+    /// Also subscribe to the notifications of the dataset version.
+    pub fn with_dataset(mut self, dataset_config: DatasetConfig) -> Self {
+        self.subscriptions
+            .push(TopicSubscription::Dataset(dataset_config));
+        self
+    }
+
+    /// Also subscribe to a topic relative to the topic base, wildcards are allowed.
+    pub fn with_topic(mut self, topic: String) -> Self {
+        self.subscriptions.push(TopicSubscription::Topic(topic));
+        self
+    }
+
+    /// The topic filters of all subscriptions.
+    pub fn topics(&self) -> Vec<String> {
+        self.subscriptions
+            .iter()
+            .map(|subscription| subscription.topic(&self.topic_base))
+            .collect()
+    }
+
+    /// Set the transport used to connect to the broker, use `MqttTransport::Tcp` to
+    /// connect to a local broker.
+    pub fn with_transport(mut self, transport: MqttTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Set the keep alive interval, the interval is rounded up to whole seconds and zero
+    /// disables keep alive.
+    pub fn with_keep_alive(mut self, keep_alive: Duration) -> Self {
+        let seconds = keep_alive.as_secs() + u64::from(keep_alive.subsec_nanos() > 0);
+        self.keep_alive = Duration::from_secs(seconds);
+        self
+    }
+
+    /// Interval of the keep alive pings, zero disables keep alive, default: 5 seconds.
+    pub fn keep_alive(&self) -> Duration {
+        self.keep_alive
+    }
+
+    /// Set the policy used to reconnect, the attempts are reset once connected.
+    pub fn with_reconnect_policy(mut self, reconnect_policy: BackoffPolicy) -> Self {
+        self.reconnect_policy = reconnect_policy;
+        self
+    }
+}
+
+/// Exponential backoff policy with jitter, used to retry failed requests.
+#[derive(Debug, Clone)]
+pub struct BackoffPolicy {
+    /// delay before the first retry
+    pub initial_delay: Duration,
+    /// upper bound for the delay between two attempts
+    pub max_delay: Duration,
+    /// factor the delay is multiplied with after every attempt
+    pub multiplier: f64,
+    /// random jitter as a fraction of the delay, 0.2 results in a delay of +/- 20%
+    pub jitter: f64,
+    /// max number of retries, None to keep retrying
+    pub max_retries: Option<u32>,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        BackoffPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_retries: Some(3),
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Policy which never retries.
+    pub fn no_retry() -> Self {
+        BackoffPolicy {
+            max_retries: Some(0),
+            ..Default::default()
+        }
+    }
+
+    /// Policy which keeps reconnecting, starting after 1 second up to once a minute.
+    pub fn reconnect() -> Self {
+        BackoffPolicy {
+            max_retries: None,
+            ..Default::default()
+        }
+    }
+
+    /// Check if another retry is allowed after the given number of retries.
+    pub fn should_retry(&self, retries: u32) -> bool {
+        !matches!(self.max_retries, Some(max) if retries >= max)
+    }
+
+    /// Returns the delay before the next attempt after the given number of retries.
+    pub fn delay(&self, retries: u32) -> Duration {
+        let max_delay = self.max_delay.as_secs_f64();
+        let mut delay = self.initial_delay.as_secs_f64()
+            * self
+                .multiplier
+                .max(1.0)
+                .powi(retries.min(i32::MAX as u32) as i32);
+
+        if self.jitter > 0.0 {
+            let jitter = self.jitter.min(1.0);
+            delay *= 1.0 + rand::thread_rng().gen_range(-jitter..=jitter);
+        }
+
+        Duration::from_secs_f64(delay.clamp(0.0, max_delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let policy = BackoffPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.0,
+            max_retries: Some(3),
+        };
+
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+        assert_eq!(policy.delay(1), Duration::from_secs(2));
+        assert_eq!(policy.delay(2), Duration::from_secs(4));
+        assert_eq!(policy.delay(10), Duration::from_secs(10));
+
+        assert!(policy.should_retry(2));
+        assert!(!policy.should_retry(3));
+        assert!(!BackoffPolicy::no_retry().should_retry(0));
+    }
+
+    #[test]
+    fn test_backoff_jitter() {
+        let policy = BackoffPolicy {
+            jitter: 0.5,
+            ..Default::default()
+        };
+
+        for _ in 0..100 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(3));
+        }
+    }
+
+    #[test]
+    fn test_known_dataset() {
+        for dataset in KnownDataset::ALL {
+            assert_eq!(KnownDataset::from_config(&dataset.config()), Some(dataset));
+        }
+
+        assert!(
+            KnownDataset::NowcastPrecipitation.matches_filename("RAD_NL25_RAC_FM_202412222055.h5")
+        );
+        assert!(KnownDataset::ActualSynopticObservations
+            .matches_filename("KMDS__OPER_P___10M_OBS_L2_202412201930.nc"));
+        assert!(KnownDataset::HarmonieCy43P1.matches_filename("HARM43_V1_P1_2024122218.tar"));
+        assert!(!KnownDataset::HarmonieCy43P1.matches_filename("RAD_NL25_RAC_FM_202412222055.h5"));
+
+        let unknown = DatasetConfig::new("radar_forecast".to_string(), "1.0".to_string());
+        assert_eq!(KnownDataset::from_config(&unknown), None);
+    }
+
+    #[test]
+    fn test_mqtt_topics() {
+        let config = MqttConfig::new_default(
+            "key".to_string(),
+            KnownDataset::NowcastPrecipitation.config(),
+        )
+        .with_dataset(KnownDataset::ActualSynopticObservations.config())
+        .with_topic("harmonie_arome_cy43_p1/+/created".to_string());
+
+        assert_eq!(
+            config.topics(),
+            vec![
+                "dataplatform/file/v1/radar_forecast/2.0/#",
+                "dataplatform/file/v1/Actuele10mindataKNMIstations/2/#",
+                "dataplatform/file/v1/harmonie_arome_cy43_p1/+/created",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mqtt_keep_alive() {
+        let config = MqttConfig::new_default(
+            "key".to_string(),
+            KnownDataset::NowcastPrecipitation.config(),
+        );
+        assert_eq!(config.keep_alive(), DEFAULT_KEEP_ALIVE);
+        assert!(matches!(config.transport, MqttTransport::Wss(_)));
+
+        let config = config.with_keep_alive(Duration::from_millis(1500));
+        assert_eq!(config.keep_alive(), Duration::from_secs(2));
+        let config = config.with_keep_alive(Duration::ZERO);
+        assert_eq!(config.keep_alive(), Duration::ZERO);
+    }
 }
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::time::Duration;
+
+/// Metadata of a dataset, returned by `GET /datasets/{datasetName}`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DatasetMetadata {
+    #[serde(alias = "datasetName")]
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(alias = "licence")]
+    pub license: Option<String>,
+    /// Available versions of the dataset
+    #[serde(deserialize_with = "deserialize_versions")]
+    pub versions: Vec<String>,
+}
+
+/// Metadata of a dataset version, returned by `GET /datasets/{datasetName}/versions/{versionId}`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DatasetVersionMetadata {
+    pub dataset_name: String,
+    #[serde(alias = "versionId", alias = "name")]
+    pub version: String,
+    pub description: Option<String>,
+    /// Number of files currently available in the dataset version
+    pub file_count: Option<u64>,
+    /// ISO 8601 duration between updates of the dataset, for example `PT5M`
+    pub update_frequency: Option<String>,
+    #[serde(alias = "licence")]
+    pub license: Option<String>,
+    pub created: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl DatasetVersionMetadata {
+    /// The update frequency as a duration, None if it is missing or can not be parsed.
+    pub fn update_interval(&self) -> Option<Duration> {
+        self.update_frequency
+            .as_deref()
+            .and_then(parse_iso8601_duration)
+    }
+}
+
+/// Versions are listed either by name or as objects containing the version name.
+fn deserialize_versions<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Version {
+        Name(String),
+        Object {
+            #[serde(alias = "versionId", alias = "name")]
+            version: String,
+        },
+    }
+
+    let versions: Option<Vec<Version>> = Option::deserialize(deserializer)?;
+    Ok(versions
+        .unwrap_or_default()
+        .into_iter()
+        .map(|v| match v {
+            Version::Name(name) => name,
+            Version::Object { version } => version,
+        })
+        .collect())
+}
+
+/// Parse an ISO 8601 duration without years and months, such as `PT10M` or `P1DT12H`.
+fn parse_iso8601_duration(value: &str) -> Option<Duration> {
+    let value = value.strip_prefix('P')?;
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, time),
+        None => (value, ""),
+    };
+
+    let mut seconds = 0.0;
+    for (part, units) in [
+        (date, &[('W', 604800.0), ('D', 86400.0)][..]),
+        (time, &[('H', 3600.0), ('M', 60.0), ('S', 1.0)][..]),
+    ] {
+        let mut number = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+
+            let (_, multiplier) = units.iter().find(|(unit, _)| *unit == c)?;
+            seconds += number.parse::<f64>().ok()? * multiplier;
+            number.clear();
+        }
+
+        if !number.is_empty() {
+            return None;
+        }
+    }
+
+    Some(Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_iso8601_duration() {
+        assert_eq!(
+            parse_iso8601_duration("PT5M"),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(
+            parse_iso8601_duration("PT1H30M"),
+            Some(Duration::from_secs(5400))
+        );
+        assert_eq!(
+            parse_iso8601_duration("P1DT12H"),
+            Some(Duration::from_secs(129600))
+        );
+        assert_eq!(parse_iso8601_duration("P1M"), None);
+        assert_eq!(parse_iso8601_duration("5 minutes"), None);
+    }
+
+    #[test]
+    fn test_deserialize_dataset_metadata() {
+        let metadata: DatasetMetadata = serde_json::from_str(
+            r#"{"datasetName": "radar_forecast", "licence": "CC BY 4.0", "versions": [{"versionId": "1.0"}, "2.0"]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.name, "radar_forecast");
+        assert_eq!(metadata.license.as_deref(), Some("CC BY 4.0"));
+        assert_eq!(metadata.versions, vec!["1.0", "2.0"]);
+        assert!(metadata.description.is_none());
+    }
+}
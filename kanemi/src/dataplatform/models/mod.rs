@@ -1,2 +1,4 @@
 pub mod config;
+pub mod metadata;
+pub mod options;
 pub mod response;
@@ -0,0 +1,171 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Field used by the Open Data API to order the file listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderBy {
+    Filename,
+    Created,
+    LastModified,
+}
+
+impl OrderBy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderBy::Filename => "filename",
+            OrderBy::Created => "created",
+            OrderBy::LastModified => "lastModified",
+        }
+    }
+}
+
+/// Sort direction of the file listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sorting {
+    Asc,
+    Desc,
+}
+
+impl Sorting {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sorting::Asc => "asc",
+            Sorting::Desc => "desc",
+        }
+    }
+}
+
+/// Options for listing the files of a dataset version.
+#[derive(Debug, Clone)]
+pub struct ListFilesOptions {
+    /// number of files to return per page, default: 500 (the API returns 10 when unset), max: 1000
+    pub max_keys: Option<u32>,
+    /// field to order the files by, default: filename
+    pub order_by: Option<OrderBy>,
+    /// sort direction, default: asc
+    pub sorting: Option<Sorting>,
+    /// only return files with a `created`/`lastModified` timestamp after this moment
+    pub begin: Option<DateTime<Utc>>,
+    /// only return files with a `created`/`lastModified` timestamp before this moment
+    pub end: Option<DateTime<Utc>>,
+    /// only return files whose filename starts with this prefix, applied client side
+    pub filename_prefix: Option<String>,
+}
+
+impl Default for ListFilesOptions {
+    fn default() -> Self {
+        Self {
+            max_keys: Some(500),
+            order_by: None,
+            sorting: None,
+            begin: None,
+            end: None,
+            filename_prefix: None,
+        }
+    }
+}
+
+impl ListFilesOptions {
+    /// Options to get the latest `max_files` files ordered by creation date.
+    pub fn latest(max_files: u32) -> Self {
+        Self {
+            max_keys: Some(max_files),
+            order_by: Some(OrderBy::Created),
+            sorting: Some(Sorting::Desc),
+            ..Default::default()
+        }
+    }
+
+    /// Construct the query parameters for a single page request, the page token
+    /// returned by the previous page can be supplied to get the next page.
+    pub fn construct_query(&self, next_page_token: Option<&str>) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+
+        if let Some(max_keys) = self.max_keys {
+            query.push(("maxKeys", max_keys.to_string()));
+        }
+
+        if let Some(order_by) = self.order_by {
+            query.push(("orderBy", order_by.as_str().to_string()));
+        }
+
+        if let Some(sorting) = self.sorting {
+            query.push(("sorting", sorting.as_str().to_string()));
+        }
+
+        if let Some(begin) = self.begin {
+            query.push(("begin", begin.to_rfc3339_opts(SecondsFormat::Secs, true)));
+        }
+
+        if let Some(end) = self.end {
+            query.push(("end", end.to_rfc3339_opts(SecondsFormat::Secs, true)));
+        }
+
+        if let Some(token) = next_page_token {
+            query.push(("nextPageToken", token.to_string()));
+        }
+
+        query
+    }
+
+    /// Check if the filename matches the filename prefix filter.
+    pub fn matches_filename(&self, filename: &str) -> bool {
+        match &self.filename_prefix {
+            Some(prefix) => filename.starts_with(prefix),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_construct_query() {
+        let options = ListFilesOptions {
+            max_keys: Some(100),
+            order_by: Some(OrderBy::Created),
+            sorting: Some(Sorting::Asc),
+            begin: Some(Utc.with_ymd_and_hms(2024, 12, 22, 20, 0, 0).unwrap()),
+            end: Some(Utc.with_ymd_and_hms(2024, 12, 22, 21, 0, 0).unwrap()),
+            filename_prefix: Some("RAD_NL25".to_string()),
+        };
+
+        let query = options.construct_query(Some("token"));
+        assert_eq!(
+            query,
+            vec![
+                ("maxKeys", "100".to_string()),
+                ("orderBy", "created".to_string()),
+                ("sorting", "asc".to_string()),
+                ("begin", "2024-12-22T20:00:00Z".to_string()),
+                ("end", "2024-12-22T21:00:00Z".to_string()),
+                ("nextPageToken", "token".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_latest() {
+        let query = ListFilesOptions::latest(1).construct_query(None);
+        assert_eq!(
+            query,
+            vec![
+                ("maxKeys", "1".to_string()),
+                ("orderBy", "created".to_string()),
+                ("sorting", "desc".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_filename() {
+        let mut options = ListFilesOptions::default();
+        assert!(options.matches_filename("RAD_NL25_RAC_FM_202412222055.h5"));
+
+        options.filename_prefix = Some("RAD_NL25_RAC_FM_2024".to_string());
+        assert!(options.matches_filename("RAD_NL25_RAC_FM_202412222055.h5"));
+        assert!(!options.matches_filename("RAD_NL25_RAC_FM_202501010000.h5"));
+    }
+}
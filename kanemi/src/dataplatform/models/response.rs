@@ -1,3 +1,4 @@
+use super::config::DatasetConfig;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -16,8 +17,11 @@ pub struct FilesResponse {
     pub result_count: i32,
     pub files: Vec<File>,
     pub max_results: i32,
+    #[serde(default)]
     pub start_after_filename: String,
-    pub next_page_token: String,
+    /// Token to request the next page, only present when the listing is truncated
+    #[serde(default)]
+    pub next_page_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -29,7 +33,40 @@ pub struct UrlResponse {
     pub temporary_download_url: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl UrlResponse {
+    /// The size of the file in bytes, None if the size can not be parsed
+    pub fn size_in_bytes(&self) -> Option<u64> {
+        self.size.parse().ok()
+    }
+}
+
+/// Error body returned by the Open Data API on a failed request.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct ErrorResponse {
+    pub error: Option<String>,
+    pub message: Option<String>,
+}
+
+impl ErrorResponse {
+    /// Returns the most descriptive message available in the error body
+    pub fn description(&self) -> Option<&str> {
+        self.message.as_deref().or(self.error.as_deref())
+    }
+}
+
+/// Rate limit information as reported by the Open Data API response headers.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct RateLimit {
+    /// max number of requests in the current window
+    pub limit: Option<u64>,
+    /// number of requests left in the current window
+    pub remaining: Option<u64>,
+    /// seconds until the window resets
+    pub reset: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct NotificationData {
     pub dataset_name: String,
@@ -38,7 +75,14 @@ pub struct NotificationData {
     pub url: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl NotificationData {
+    /// The dataset version the notification belongs to.
+    pub fn dataset_config(&self) -> DatasetConfig {
+        DatasetConfig::new(self.dataset_name.clone(), self.dataset_version.clone())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct NotificationReponse {
     pub specversion: String,
@@ -1,31 +1,211 @@
-use super::models::config::MqttConfig;
+use super::models::config::{BackoffPolicy, DatasetConfig, MqttConfig};
 use super::models::response::NotificationReponse;
-use crate::errors::NotificationError;
-use rumqttc::{AsyncClient, MqttOptions, QoS, TlsConfiguration, Transport};
-use std::sync::atomic::{AtomicBool, Ordering};
+use super::store::NotificationStore;
+use crate::errors::{ConnectionFailure, NotificationError};
+use futures::{Stream, StreamExt};
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS, SubscribeFilter, SubscribeReasonCode};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio_util::sync::CancellationToken;
 
 type NotificationMessageHandler = Arc<dyn Fn(String, NotificationReponse) + Send + Sync>;
 type NotificationErrorHandler = Arc<dyn Fn(NotificationError) + Send + Sync>;
 
+/// Number of events buffered by the notification stream before the connection is paused.
+const EVENT_BUFFER_SIZE: usize = 100;
+
+/// State of the connection with the MQTT broker, the `FilePoller` reports `Subscribed`
+/// after a successful poll and `Disconnected` after a failed poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Connecting to the broker
+    Connecting,
+    /// Connected to the broker, not subscribed yet
+    Connected,
+    /// Subscribed to at least one topic, notifications will be received. Topics which were
+    /// rejected by the broker are reported with `NotificationError::SubscriptionError`.
+    Subscribed,
+    /// Connecting failed or the connection was lost, a reconnect follows
+    Disconnected,
+    /// The service was stopped, no more events will follow
+    Stopped,
+}
+
+/// Event emitted by the notification stream.
+#[derive(Debug)]
+pub enum NotificationEvent {
+    /// A notification was received on the topic, the dataset is taken from the notification
+    Notification {
+        topic: String,
+        dataset: DatasetConfig,
+        notification: NotificationReponse,
+    },
+    /// The state of the connection changed
+    StateChanged(ConnectionState),
+    /// A message was received which could not be parsed as notification
+    ParseError {
+        topic: String,
+        payload: String,
+        error: NotificationError,
+    },
+    /// Connecting or subscribing failed, see `ConnectionFailure::retry_in` to check if a
+    /// reconnect follows
+    Error(NotificationError),
+}
+
+/// Stream of notification events, the connection is handled by a background task which
+/// stops when the stream is stopped, cancelled or dropped.
+pub struct NotificationStream {
+    receiver: mpsc::Receiver<NotificationEvent>,
+    state: watch::Receiver<ConnectionState>,
+    cancellation_token: CancellationToken,
+}
+
+impl NotificationStream {
+    /// Stop the stream, the stream ends after the `ConnectionState::Stopped` event.
+    pub fn stop(&self) {
+        self.cancellation_token.cancel();
+    }
+
+    /// Returns the current connection state, can be used for health checks.
+    pub fn state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    /// Receiver which is notified on every state change, unlike the events in the stream
+    /// state changes can be observed without consuming the notifications.
+    pub fn state_receiver(&self) -> watch::Receiver<ConnectionState> {
+        self.state.clone()
+    }
+
+    /// Token which stops the stream when cancelled.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation_token.clone()
+    }
+}
+
+impl NotificationStream {
+    /// Create a stream and the sender used by a background task to feed it.
+    pub(crate) fn channel(cancellation_token: CancellationToken) -> (Self, EventSender) {
+        let (events, receiver) = mpsc::channel(EVENT_BUFFER_SIZE);
+        let (state_sender, state) = watch::channel(ConnectionState::Connecting);
+        let sender = EventSender {
+            events,
+            state: state_sender,
+        };
+
+        let stream = NotificationStream {
+            receiver,
+            state,
+            cancellation_token,
+        };
+
+        (stream, sender)
+    }
+}
+
+impl Stream for NotificationStream {
+    type Item = NotificationEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for NotificationStream {
+    fn drop(&mut self) {
+        self.cancellation_token.cancel();
+    }
+}
+
 pub struct NotificationService {
     mqtt_config: MqttConfig,
-    stop_flag: Arc<AtomicBool>,
+    cancellation_token: CancellationToken,
+    store: Option<Arc<NotificationStore>>,
 }
 
 impl NotificationService {
     pub fn new(mqtt_config: MqttConfig) -> Self {
         NotificationService {
             mqtt_config,
-            stop_flag: Arc::new(AtomicBool::new(false)),
+            cancellation_token: CancellationToken::new(),
+            store: None,
         }
     }
 
+    /// Use the given token to stop the service and all its streams, for example the
+    /// shutdown token of an application.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Use the store to suppress notifications which were handled before. The consumer of
+    /// the stream acknowledges a notification with `NotificationStore::acknowledge` once it
+    /// is handled, `DownloadPipeline::with_store` does so after the download and `start`
+    /// after the handler returned.
+    pub fn with_store(mut self, store: Arc<NotificationStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Stop the service and all its streams.
     pub fn stop(&self) {
-        self.stop_flag.store(true, Ordering::Relaxed);
+        self.cancellation_token.cancel();
+    }
+
+    /// Connect to the broker and return a stream of notification events. The connection is
+    /// restored according to the reconnect policy of the config until the stream is stopped,
+    /// dropped or the reconnect attempts are exhausted.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use futures::StreamExt;
+    /// use kanemi::dataplatform::models::config::{KnownDataset, MqttConfig};
+    /// use kanemi::dataplatform::notification::{NotificationEvent, NotificationService};
+    ///
+    /// # async fn example() {
+    /// let config = MqttConfig::new_default(
+    ///     "api-key".to_string(),
+    ///     KnownDataset::NowcastPrecipitation.config(),
+    /// );
+    /// let service = NotificationService::new(config);
+    /// let mut stream = service.stream("client-id".to_string(), false);
+    ///
+    /// loop {
+    ///     tokio::select! {
+    ///         Some(event) = stream.next() => {
+    ///             if let NotificationEvent::Notification { notification, .. } = event {
+    ///                 println!("{}", notification.data.filename);
+    ///             }
+    ///         }
+    ///         _ = tokio::signal::ctrl_c() => break,
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn stream(&self, user_id: String, clean_session: bool) -> NotificationStream {
+        let cancellation_token = self.cancellation_token.child_token();
+        let (stream, sender) = NotificationStream::channel(cancellation_token.clone());
+
+        tokio::spawn(run_event_loop(
+            self.mqtt_config.clone(),
+            user_id,
+            clean_session,
+            self.store.clone(),
+            sender,
+            cancellation_token,
+        ));
+
+        stream
     }
 
+    /// Connect to the broker and call the handler for every notification until the service
+    /// is stopped. Connection errors and messages which can not be parsed are passed to
+    /// the error handler.
     pub async fn start(
         &mut self,
         user_id: String,
@@ -33,63 +213,345 @@ impl NotificationService {
         handler: NotificationMessageHandler,
         error_handler: NotificationErrorHandler,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let topic = format!(
-            "{}/{}/{}/#",
-            self.mqtt_config.topic_base,
-            self.mqtt_config.dataset_config.dataset_name,
-            self.mqtt_config.dataset_config.version
-        );
+        let mut stream = self.stream(user_id, clean_session);
 
-        while !self.stop_flag.load(Ordering::Relaxed) {
-            let mut mqttoptions = MqttOptions::new(
-                user_id.clone(),
-                &self.mqtt_config.broker,
-                self.mqtt_config.port,
-            );
-            mqttoptions.set_transport(Transport::Wss(TlsConfiguration::default()));
-            mqttoptions.set_keep_alive(Duration::from_secs(5));
-            mqttoptions.set_credentials(user_id.clone(), &self.mqtt_config.api_key);
-            mqttoptions.set_clean_session(clean_session);
-
-            let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
-
-            if let Err(e) = client.subscribe(&topic, QoS::AtLeastOnce).await {
-                error_handler(NotificationError::SubscriptionError(e.to_string()));
-                tokio::time::sleep(Duration::from_secs(10)).await;
-                continue;
+        while let Some(event) = stream.next().await {
+            match event {
+                NotificationEvent::Notification {
+                    topic,
+                    notification,
+                    ..
+                } => {
+                    handler(topic, notification.clone());
+                    if let Some(store) = &self.store {
+                        if let Err(error) = store.acknowledge(&notification).await {
+                            error_handler(error);
+                        }
+                    }
+                }
+                NotificationEvent::ParseError { error, .. } => error_handler(error),
+                NotificationEvent::Error(error) => error_handler(error),
+                NotificationEvent::StateChanged(_) => {}
             }
+        }
+
+        Ok(())
+    }
+}
+
+/// Sends events and state changes of the event loop to the stream.
+pub(crate) struct EventSender {
+    events: mpsc::Sender<NotificationEvent>,
+    state: watch::Sender<ConnectionState>,
+}
+
+impl EventSender {
+    /// Send the event to the stream, returns false when the stream was dropped.
+    pub(crate) async fn emit(&self, event: NotificationEvent) -> bool {
+        self.events.send(event).await.is_ok()
+    }
+
+    /// Update the connection state and send it to the stream, returns false when the
+    /// stream was dropped.
+    pub(crate) async fn set_state(&self, state: ConnectionState) -> bool {
+        self.state.send_replace(state);
+        self.emit(NotificationEvent::StateChanged(state)).await
+    }
+}
+
+/// Register a failed attempt and return the delay before the next attempt, None when
+/// the reconnect attempts are exhausted.
+fn next_delay(policy: &BackoffPolicy, failures: &mut u32) -> Option<Duration> {
+    let delay = policy
+        .should_retry(*failures)
+        .then(|| policy.delay(*failures));
+    *failures = failures.saturating_add(1);
+    delay
+}
 
-            loop {
-                match eventloop.poll().await {
-                    // print poll
-                    Ok(notification) => {
-                        if let rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish)) =
-                            notification
-                        {
-                            let payload_json = String::from_utf8_lossy(&publish.payload);
-                            if let Ok(notification) =
-                                serde_json::from_str::<NotificationReponse>(&payload_json)
-                            {
-                                handler(publish.topic, notification);
-                            }
+/// Wait for the delay, returns false when cancelled while waiting.
+pub(crate) async fn wait(cancellation_token: &CancellationToken, delay: Duration) -> bool {
+    tokio::select! {
+        _ = cancellation_token.cancelled() => false,
+        _ = tokio::time::sleep(delay) => true,
+    }
+}
+
+/// Topic filters rejected by the broker, the return codes are in the order of the filters.
+fn rejected_filters(topics: &[String], return_codes: &[SubscribeReasonCode]) -> Vec<String> {
+    topics
+        .iter()
+        .zip(return_codes)
+        .filter(|(_, code)| matches!(code, SubscribeReasonCode::Failure))
+        .map(|(topic, _)| topic.clone())
+        .collect()
+}
+
+fn parse_notification(topic: String, payload: &[u8]) -> NotificationEvent {
+    let payload = String::from_utf8_lossy(payload).to_string();
+    match serde_json::from_str::<NotificationReponse>(&payload) {
+        Ok(notification) => NotificationEvent::Notification {
+            topic,
+            dataset: notification.data.dataset_config(),
+            notification,
+        },
+        Err(e) => NotificationEvent::ParseError {
+            topic,
+            payload,
+            error: NotificationError::ParseError(e.to_string()),
+        },
+    }
+}
+
+async fn run_event_loop(
+    mqtt_config: MqttConfig,
+    user_id: String,
+    clean_session: bool,
+    store: Option<Arc<NotificationStore>>,
+    sender: EventSender,
+    cancellation_token: CancellationToken,
+) {
+    let topics = mqtt_config.topics();
+    if topics.is_empty() {
+        let error = NotificationError::SubscriptionError("No topics to subscribe to".to_string());
+        let _ = sender.emit(NotificationEvent::Error(error)).await;
+        let _ = sender.set_state(ConnectionState::Stopped).await;
+        return;
+    }
+
+    let policy = &mqtt_config.reconnect_policy;
+    // number of consecutive failed attempts, reset once connected
+    let mut failures: u32 = 0;
+
+    while !cancellation_token.is_cancelled() {
+        if !sender.set_state(ConnectionState::Connecting).await {
+            return;
+        }
+
+        let mut mqttoptions =
+            MqttOptions::new(user_id.clone(), &mqtt_config.broker, mqtt_config.port);
+        mqttoptions.set_transport(mqtt_config.transport.transport());
+        mqttoptions.set_keep_alive(mqtt_config.keep_alive());
+        mqttoptions.set_credentials(user_id.clone(), &mqtt_config.api_key);
+        mqttoptions.set_clean_session(clean_session);
+
+        let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+
+        let filters = topics
+            .iter()
+            .map(|topic| SubscribeFilter::new(topic.clone(), QoS::AtLeastOnce));
+        if let Err(e) = client.subscribe_many(filters).await {
+            let retry_in = next_delay(policy, &mut failures);
+            let error = NotificationError::SubscriptionError(e.to_string());
+            if !sender.emit(NotificationEvent::Error(error)).await {
+                return;
+            }
+
+            match retry_in {
+                Some(delay) if wait(&cancellation_token, delay).await => continue,
+                _ => break,
+            }
+        }
+
+        let retry_in = loop {
+            let event = tokio::select! {
+                _ = cancellation_token.cancelled() => {
+                    let _ = client.try_disconnect();
+                    break None;
+                }
+                event = eventloop.poll() => event,
+            };
+
+            let event = match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    failures = 0;
+                    if !sender.set_state(ConnectionState::Connected).await {
+                        return;
+                    }
+                    continue;
+                }
+                Ok(Event::Incoming(Packet::SubAck(suback))) => {
+                    let rejected = rejected_filters(&topics, &suback.return_codes);
+                    if !rejected.is_empty() {
+                        let error = NotificationError::SubscriptionError(format!(
+                            "Subscription rejected by the broker: {}",
+                            rejected.join(", ")
+                        ));
+                        if !sender.emit(NotificationEvent::Error(error)).await {
+                            return;
                         }
                     }
-                    Err(_) => {
-                        break; // Exit loop and retry after sleep
+
+                    let granted = suback
+                        .return_codes
+                        .iter()
+                        .any(|code| matches!(code, SubscribeReasonCode::Success(_)));
+                    if granted && !sender.set_state(ConnectionState::Subscribed).await {
+                        return;
+                    }
+                    continue;
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    parse_notification(publish.topic, &publish.payload)
+                }
+                Ok(_) => continue,
+                Err(cause) => {
+                    let retry_in = next_delay(policy, &mut failures);
+
+                    let error = NotificationError::ConnectionFailed(ConnectionFailure {
+                        cause: Box::new(cause),
+                        attempt: failures,
+                        retry_in,
+                    });
+                    if !sender.emit(NotificationEvent::Error(error)).await {
+                        return;
                     }
+                    break retry_in;
                 }
+            };
 
-                if self.stop_flag.load(Ordering::Relaxed) {
-                    return Ok(());
+            if let (Some(store), NotificationEvent::Notification { notification, .. }) =
+                (&store, &event)
+            {
+                // handled before, for example redelivered after a reconnect
+                if !store.receive(notification) {
+                    continue;
                 }
             }
 
-            error_handler(NotificationError::ConnectionError(
-                "Connection lost, retrying in 10 seconds".to_string(),
-            ));
-            tokio::time::sleep(Duration::from_secs(10)).await;
+            if !sender.emit(event).await {
+                return;
+            }
+        };
+
+        let delay = match retry_in {
+            Some(delay) if !cancellation_token.is_cancelled() => delay,
+            _ => break,
+        };
+
+        if !sender.set_state(ConnectionState::Disconnected).await
+            || !wait(&cancellation_token, delay).await
+        {
+            break;
         }
+    }
 
-        Ok(())
+    let _ = sender.set_state(ConnectionState::Stopped).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataplatform::models::config::KnownDataset;
+
+    #[test]
+    fn test_parse_notification() {
+        let payload = r#"{
+            "specversion": "1.0",
+            "type": "nl.knmi.dataplatform.file.created.v1",
+            "source": "https://dataplatform.knmi.nl",
+            "id": "1",
+            "time": "2025-01-03T14:56:43Z",
+            "datacontenttype": "application/json",
+            "data": {
+                "datasetName": "radar_forecast",
+                "datasetVersion": "2.0",
+                "filename": "RAD_NL25_RAC_FM_202501031455.h5",
+                "url": "https://example.com"
+            }
+        }"#;
+
+        match parse_notification("topic".to_string(), payload.as_bytes()) {
+            NotificationEvent::Notification {
+                dataset,
+                notification,
+                ..
+            } => {
+                assert_eq!(dataset, KnownDataset::NowcastPrecipitation.config());
+                assert_eq!(
+                    notification.data.filename,
+                    "RAD_NL25_RAC_FM_202501031455.h5"
+                )
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+
+        assert!(matches!(
+            parse_notification("topic".to_string(), b"not json"),
+            NotificationEvent::ParseError { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stream_stops() {
+        let dataset_config = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+        let mqtt_config = MqttConfig::new(
+            "key".to_string(),
+            dataset_config,
+            "127.0.0.1".to_string(),
+            1,
+            "dataplatform/file/v1".to_string(),
+        );
+        let service = NotificationService::new(mqtt_config);
+        let mut stream = service.stream("client".to_string(), true);
+
+        assert!(matches!(
+            stream.next().await,
+            Some(NotificationEvent::StateChanged(ConnectionState::Connecting))
+        ));
+        assert!(matches!(
+            stream.next().await,
+            Some(NotificationEvent::Error(
+                NotificationError::ConnectionFailed(_)
+            ))
+        ));
+
+        service.stop();
+        let mut last = None;
+        while let Some(event) = stream.next().await {
+            last = Some(event);
+        }
+        assert!(matches!(
+            last,
+            Some(NotificationEvent::StateChanged(ConnectionState::Stopped))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_policy() {
+        let dataset_config = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+        let mqtt_config = MqttConfig::new(
+            "key".to_string(),
+            dataset_config,
+            "127.0.0.1".to_string(),
+            1,
+            "dataplatform/file/v1".to_string(),
+        )
+        .with_reconnect_policy(BackoffPolicy {
+            initial_delay: Duration::from_millis(10),
+            jitter: 0.0,
+            max_retries: Some(1),
+            ..Default::default()
+        });
+        let service = NotificationService::new(mqtt_config);
+        let stream = service.stream("client".to_string(), true);
+        let state = stream.state_receiver();
+
+        let failures: Vec<ConnectionFailure> = stream
+            .filter_map(|event| async move {
+                match event {
+                    NotificationEvent::Error(NotificationError::ConnectionFailed(e)) => Some(e),
+                    _ => None,
+                }
+            })
+            .collect()
+            .await;
+
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].attempt, 1);
+        assert_eq!(failures[0].retry_in, Some(Duration::from_millis(10)));
+        assert_eq!(failures[1].attempt, 2);
+        assert_eq!(failures[1].retry_in, None);
+        assert_eq!(*state.borrow(), ConnectionState::Stopped);
     }
 }
@@ -0,0 +1,499 @@
+use super::api::{output_filepath, OpenDataAPI, DEFAULT_DOWNLOAD_CONCURRENCY};
+use super::models::response::NotificationReponse;
+use super::notification::NotificationEvent;
+use super::store::NotificationStore;
+use crate::errors::{ApiError, NotificationError};
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Number of notification ids remembered to detect duplicate notifications.
+const SEEN_IDS_CAPACITY: usize = 10_000;
+
+/// A file downloaded by the pipeline.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadedFile {
+    /// Id of the notification which triggered the download
+    pub id: String,
+    pub dataset_name: String,
+    pub dataset_version: String,
+    pub filename: String,
+    /// Path of the downloaded file
+    pub path: String,
+}
+
+/// Errors reported by the download pipeline, the pipeline keeps running after an error.
+#[derive(Debug)]
+pub enum PipelineError {
+    /// Error reported by the notification source
+    Notification(NotificationError),
+    /// The file of a notification could not be downloaded
+    Download {
+        id: String,
+        filename: String,
+        error: ApiError,
+    },
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineError::Notification(e) => write!(f, "{}", e),
+            PipelineError::Download {
+                id,
+                filename,
+                error,
+            } => write!(
+                f,
+                "Download of {} (notification {}) failed: {}",
+                filename, id, error
+            ),
+        }
+    }
+}
+
+pub type DownloadHook = Arc<dyn Fn(DownloadedFile) + Send + Sync>;
+pub type PipelineErrorHandler = Arc<dyn Fn(PipelineError) + Send + Sync>;
+
+/// Bounded set of notification ids which have been handled, the oldest ids are forgotten
+/// when the capacity is reached.
+#[derive(Debug)]
+struct SeenIds {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl SeenIds {
+    fn new(capacity: usize) -> Self {
+        SeenIds {
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Mark the id as seen, returns false if it was seen before.
+    fn insert(&mut self, id: &str) -> bool {
+        if !self.ids.insert(id.to_string()) {
+            return false;
+        }
+
+        self.order.push_back(id.to_string());
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+
+        true
+    }
+
+    fn remove(&mut self, id: &str) {
+        if self.ids.remove(id) {
+            self.order.retain(|seen| seen != id);
+        }
+    }
+}
+
+/// Downloads the file of every notification received from a notification source, such as
+/// the stream of `NotificationService`, to the output path. Notifications are deduplicated
+/// by their id and the download hook is called for every completed file.
+///
+/// # Example
+/// ```no_run
+/// use kanemi::dataplatform::api::OpenDataAPI;
+/// use kanemi::dataplatform::models::config::{KnownDataset, MqttConfig};
+/// use kanemi::dataplatform::notification::NotificationService;
+/// use kanemi::dataplatform::pipeline::{DownloadPipeline, DownloadedFile};
+/// use std::sync::Arc;
+///
+/// # async fn example() {
+/// let oda = OpenDataAPI::new("api-key".to_string());
+/// let config = MqttConfig::new_default(
+///     "notification-api-key".to_string(),
+///     KnownDataset::NowcastPrecipitation.config(),
+/// );
+/// let service = NotificationService::new(config);
+///
+/// let pipeline = DownloadPipeline::new(oda, "./output".to_string()).with_hook(Arc::new(
+///     |file: DownloadedFile| println!("Downloaded {}", file.path),
+/// ));
+/// pipeline.run(service.stream("client-id".to_string(), false)).await;
+/// # }
+/// ```
+pub struct DownloadPipeline {
+    oda: OpenDataAPI,
+    output_path: String,
+    concurrency: usize,
+    hook: Option<DownloadHook>,
+    error_handler: Option<PipelineErrorHandler>,
+    seen_ids: Mutex<SeenIds>,
+    store: Option<Arc<NotificationStore>>,
+}
+
+impl DownloadPipeline {
+    pub fn new(oda: OpenDataAPI, output_path: String) -> Self {
+        DownloadPipeline {
+            oda,
+            output_path,
+            concurrency: DEFAULT_DOWNLOAD_CONCURRENCY,
+            hook: None,
+            error_handler: None,
+            seen_ids: Mutex::new(SeenIds::new(SEEN_IDS_CAPACITY)),
+            store: None,
+        }
+    }
+
+    /// Set the max number of concurrent downloads.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set a hook which is called for every completed file.
+    pub fn with_hook(mut self, hook: DownloadHook) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    /// Set a handler which is called for errors of the notification source and failed
+    /// downloads.
+    pub fn with_error_handler(mut self, error_handler: PipelineErrorHandler) -> Self {
+        self.error_handler = Some(error_handler);
+        self
+    }
+
+    /// Use the store to skip notifications which were handled before, every notification is
+    /// acknowledged in the store after the file is downloaded. Use the same store as the
+    /// `NotificationService` so failed downloads are not suppressed after a restart.
+    pub fn with_store(mut self, store: Arc<NotificationStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Download the files of all notifications in the stream, returns when the stream ends.
+    pub async fn run<S>(&self, events: S)
+    where
+        S: Stream<Item = NotificationEvent>,
+    {
+        events
+            .for_each_concurrent(self.concurrency, |event| async move {
+                match event {
+                    NotificationEvent::Notification { notification, .. } => {
+                        match self.handle_notification(&notification).await {
+                            Some(Ok(file)) => {
+                                if let Some(hook) = &self.hook {
+                                    hook(file);
+                                }
+                            }
+                            Some(Err(error)) => self.report(error),
+                            None => {}
+                        }
+                    }
+                    NotificationEvent::ParseError { error, .. }
+                    | NotificationEvent::Error(error) => {
+                        self.report(PipelineError::Notification(error))
+                    }
+                    NotificationEvent::StateChanged(_) => {}
+                }
+            })
+            .await;
+    }
+
+    /// Download the file of the notification, returns None when the notification was
+    /// already handled. A notification which failed to download can be retried, it is
+    /// only acknowledged in the store after a successful download.
+    pub async fn handle_notification(
+        &self,
+        notification: &NotificationReponse,
+    ) -> Option<Result<DownloadedFile, PipelineError>> {
+        if let Some(store) = &self.store {
+            if store.contains(&notification.id) {
+                return None;
+            }
+        }
+
+        if !self.seen_ids.lock().unwrap().insert(&notification.id) {
+            return None;
+        }
+
+        let result = self.download(notification).await;
+        match (&result, &self.store) {
+            (Err(_), _) => self.seen_ids.lock().unwrap().remove(&notification.id),
+            (Ok(_), Some(store)) => {
+                // the file is downloaded, a failed write of the store is only reported
+                if let Err(error) = store.acknowledge(notification).await {
+                    self.report(PipelineError::Notification(error));
+                }
+            }
+            (Ok(_), None) => {}
+        }
+
+        Some(result.map_err(|error| PipelineError::Download {
+            id: notification.id.clone(),
+            filename: notification.data.filename.clone(),
+            error,
+        }))
+    }
+
+    async fn download(
+        &self,
+        notification: &NotificationReponse,
+    ) -> Result<DownloadedFile, ApiError> {
+        let data = &notification.data;
+        // the filename is part of the notification and should not escape the output path
+        let path = output_filepath(&self.output_path, &data.filename)?;
+        let response = self.oda.get_notification_download_url(data).await?;
+
+        self.oda
+            .download_file(
+                &response.temporary_download_url,
+                &path,
+                Some(false),
+                response.size_in_bytes(),
+            )
+            .await?;
+
+        Ok(DownloadedFile {
+            id: notification.id.clone(),
+            dataset_name: data.dataset_name.clone(),
+            dataset_version: data.dataset_version.clone(),
+            filename: data.filename.clone(),
+            path,
+        })
+    }
+
+    fn report(&self, error: PipelineError) {
+        if let Some(error_handler) = &self.error_handler {
+            error_handler(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataplatform::models::config::DatasetConfig;
+    use crate::dataplatform::models::response::NotificationData;
+    use futures::stream;
+
+    fn notification(id: &str, filename: &str, url: String) -> NotificationEvent {
+        let data = NotificationData {
+            dataset_name: "radar_forecast".to_string(),
+            dataset_version: "2.0".to_string(),
+            filename: filename.to_string(),
+            url,
+        };
+
+        NotificationEvent::Notification {
+            topic: "dataplatform/file/v1/radar_forecast/2.0/created".to_string(),
+            dataset: data.dataset_config(),
+            notification: NotificationReponse {
+                specversion: "1.0".to_string(),
+                notification_type: "nl.knmi.dataplatform.file.created.v1".to_string(),
+                source: "https://dataplatform.knmi.nl".to_string(),
+                id: id.to_string(),
+                time: "2025-01-03T14:57:16Z".to_string(),
+                datacontenttype: "application/json".to_string(),
+                data,
+            },
+        }
+    }
+
+    #[test]
+    fn test_seen_ids() {
+        let mut seen = SeenIds::new(2);
+        assert!(seen.insert("1"));
+        assert!(!seen.insert("1"));
+        assert!(seen.insert("2"));
+        assert!(seen.insert("3"));
+        // the oldest id is forgotten
+        assert!(seen.insert("1"));
+        seen.remove("1");
+        assert!(seen.insert("1"));
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_deduplicates_notifications() {
+        let mut server = mockito::Server::new_async().await;
+        let files_path = "/datasets/radar_forecast/versions/2.0/files";
+        let mut url_mocks = Vec::new();
+        for filename in ["A.h5", "B.h5"] {
+            url_mocks.push(
+                server
+                    .mock("GET", format!("{}/{}/url", files_path, filename).as_str())
+                    .with_body(
+                        serde_json::json!({
+                            "contentType": "application/x-hdf5",
+                            "lastModified": "2025-01-03T14:57:16Z",
+                            "size": "4",
+                            "temporaryDownloadUrl": format!("{}/download/{}", server.url(), filename)
+                        })
+                        .to_string(),
+                    )
+                    .expect(1)
+                    .create_async()
+                    .await,
+            );
+        }
+        let download = server
+            .mock("GET", mockito::Matcher::Regex("^/download/".to_string()))
+            .with_body("0123")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let downloaded = Arc::new(Mutex::new(Vec::new()));
+        let downloaded_clone = downloaded.clone();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let errors_clone = errors.clone();
+        let pipeline = DownloadPipeline::new(oda, dir.path().to_string_lossy().to_string())
+            .with_concurrency(1)
+            .with_hook(Arc::new(move |file: DownloadedFile| {
+                downloaded_clone.lock().unwrap().push(file.filename)
+            }))
+            .with_error_handler(Arc::new(move |error: PipelineError| {
+                errors_clone.lock().unwrap().push(error.to_string())
+            }));
+
+        let url = |filename: &str| format!("{}{}/{}/url", server.url(), files_path, filename);
+        let events = stream::iter(vec![
+            notification("1", "A.h5", url("A.h5")),
+            notification("1", "A.h5", url("A.h5")),
+            // the url of the notification is not used for other hosts
+            notification("2", "B.h5", "https://example.com/B.h5/url".to_string()),
+            NotificationEvent::Error(NotificationError::ParseError("invalid".to_string())),
+        ]);
+        pipeline.run(events).await;
+
+        assert_eq!(*downloaded.lock().unwrap(), vec!["A.h5", "B.h5"]);
+        assert_eq!(errors.lock().unwrap().len(), 1);
+        assert!(dir.path().join("A.h5").exists());
+        assert!(dir.path().join("B.h5").exists());
+
+        for mock in url_mocks {
+            mock.assert_async().await;
+        }
+        download.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_download_is_redelivered_after_restart() {
+        let mut server = mockito::Server::new_async().await;
+        let url_path = "/datasets/radar_forecast/versions/2.0/files/A.h5/url";
+        let url_mock = server
+            .mock("GET", url_path)
+            .with_body(
+                serde_json::json!({
+                    "contentType": "application/x-hdf5",
+                    "lastModified": "2025-01-03T14:57:16Z",
+                    "size": "4",
+                    "temporaryDownloadUrl": format!("{}/download/A.h5", server.url())
+                })
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+        let failed_download = server
+            .mock("GET", "/download/A.h5")
+            .with_status(404)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let output_path = dir.path().to_path_buf();
+        let store_path = dir.path().join("store.json");
+        let dataset = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+        let url = format!("{}{}", server.url(), url_path);
+        let event = || notification("1", "A.h5", url.clone());
+
+        let store = Arc::new(NotificationStore::open(&store_path).unwrap());
+        let pipeline =
+            DownloadPipeline::new(oda.clone(), output_path.to_string_lossy().to_string())
+                .with_store(store.clone());
+        let NotificationEvent::Notification { notification, .. } = event() else {
+            unreachable!()
+        };
+        assert!(store.receive(&notification));
+        assert!(matches!(
+            pipeline.handle_notification(&notification).await,
+            Some(Err(PipelineError::Download { .. }))
+        ));
+        assert!(!store.contains("1"));
+        assert!(store.last_event_time(&dataset).is_none());
+        failed_download.assert_async().await;
+        failed_download.remove_async().await;
+        drop(pipeline);
+        drop(store);
+
+        // restart, the notification which failed is delivered again
+        let download = server
+            .mock("GET", "/download/A.h5")
+            .with_body("0123")
+            .expect(1)
+            .create_async()
+            .await;
+        let store = Arc::new(NotificationStore::open(&store_path).unwrap());
+        assert!(store.receive(&notification));
+        let downloaded = Arc::new(Mutex::new(Vec::new()));
+        let downloaded_clone = downloaded.clone();
+        let pipeline = DownloadPipeline::new(oda, output_path.to_string_lossy().to_string())
+            .with_store(store.clone())
+            .with_hook(Arc::new(move |file: DownloadedFile| {
+                downloaded_clone.lock().unwrap().push(file.filename)
+            }));
+        pipeline.run(stream::iter(vec![event(), event()])).await;
+
+        assert_eq!(*downloaded.lock().unwrap(), vec!["A.h5"]);
+        assert!(output_path.join("A.h5").exists());
+        assert!(store.contains("1"));
+        assert!(NotificationStore::open(&store_path).unwrap().contains("1"));
+        url_mock.assert_async().await;
+        download.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_rejects_filenames_outside_output_path() {
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url("http://127.0.0.1:1".to_string())
+            .build()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let output_path = dir.path().join("output");
+        let pipeline = DownloadPipeline::new(oda, output_path.to_string_lossy().to_string());
+
+        for (id, filename) in [("1", "../x.h5"), ("2", "/tmp/x.h5")] {
+            let NotificationEvent::Notification { notification, .. } =
+                notification(id, filename, "https://example.com".to_string())
+            else {
+                unreachable!()
+            };
+
+            match pipeline.handle_notification(&notification).await {
+                Some(Err(PipelineError::Download { error, .. })) => {
+                    assert!(matches!(error, ApiError::InvalidFilename(_)))
+                }
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+        assert!(!dir.path().join("x.h5").exists());
+    }
+}
@@ -0,0 +1,473 @@
+use super::api::OpenDataAPI;
+use super::models::config::DatasetConfig;
+use super::models::options::ListFilesOptions;
+use super::models::response::{File, NotificationData, NotificationReponse};
+use super::notification::{
+    wait, ConnectionState, EventSender, NotificationEvent, NotificationStream,
+};
+use super::store::NotificationStore;
+use crate::errors::NotificationError;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Default interval between two polls of the file listing.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Default number of latest files requested per poll.
+pub const DEFAULT_POLL_MAX_FILES: u32 = 100;
+
+const NOTIFICATION_SPECVERSION: &str = "1.0";
+const NOTIFICATION_TYPE: &str = "nl.knmi.dataplatform.file.created.v1";
+const NOTIFICATION_SOURCE: &str = "https://dataplatform.knmi.nl";
+const NOTIFICATION_CONTENT_TYPE: &str = "application/json";
+const TOPIC_BASE: &str = "dataplatform/file/v1";
+
+/// Polls the file listing of the Open Data API for new files, a fallback for the
+/// `NotificationService` when no notification API key is available or MQTT is blocked.
+/// The poller emits the same events on the same `NotificationStream`, new files are
+/// reported as `NotificationEvent::Notification` with a notification created from the
+/// file listing. The id of these notifications is `dataset/version/filename`, which differs
+/// from the id of the MQTT notification of the same file.
+///
+/// Files created since the stream was started are reported, also when the first polls
+/// failed. With a store only the files created after the last notification in the store
+/// are reported, so files handled from MQTT notifications or by an earlier run are not
+/// reported again. The state of the stream is `Subscribed` after a successful poll and
+/// `Disconnected` after a failed poll, polling continues until the stream is stopped.
+///
+/// # Example
+/// ```no_run
+/// use futures::StreamExt;
+/// use kanemi::dataplatform::api::OpenDataAPI;
+/// use kanemi::dataplatform::models::config::KnownDataset;
+/// use kanemi::dataplatform::notification::NotificationEvent;
+/// use kanemi::dataplatform::poller::FilePoller;
+///
+/// # async fn example() {
+/// let oda = OpenDataAPI::new("api-key".to_string());
+/// let poller = FilePoller::new(oda, vec![KnownDataset::NowcastPrecipitation.config()]);
+/// let mut stream = poller.stream();
+///
+/// while let Some(event) = stream.next().await {
+///     if let NotificationEvent::Notification { notification, .. } = event {
+///         println!("{}", notification.data.filename);
+///     }
+/// }
+/// # }
+/// ```
+pub struct FilePoller {
+    oda: OpenDataAPI,
+    datasets: Vec<DatasetConfig>,
+    interval: Duration,
+    max_files: u32,
+    store: Option<Arc<NotificationStore>>,
+    cancellation_token: CancellationToken,
+}
+
+impl FilePoller {
+    pub fn new(oda: OpenDataAPI, datasets: Vec<DatasetConfig>) -> Self {
+        FilePoller {
+            oda,
+            datasets,
+            interval: DEFAULT_POLL_INTERVAL,
+            max_files: DEFAULT_POLL_MAX_FILES,
+            store: None,
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    /// Set the interval between two polls.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the number of latest files requested per poll, should be larger than the
+    /// number of files created in an interval to not miss any files.
+    pub fn with_max_files(mut self, max_files: u32) -> Self {
+        self.max_files = max_files.max(1);
+        self
+    }
+
+    /// Only report files created after the last notification of the dataset in the store,
+    /// see `NotificationStore::last_event_time`. Use the store of the `DownloadPipeline` or
+    /// `NotificationService` the events are combined with.
+    pub fn with_store(mut self, store: Arc<NotificationStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Use the given token to stop the poller and all its streams.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Stop the poller and all its streams.
+    pub fn stop(&self) {
+        self.cancellation_token.cancel();
+    }
+
+    /// Start polling and return a stream of notification events.
+    pub fn stream(&self) -> NotificationStream {
+        let cancellation_token = self.cancellation_token.child_token();
+        let (stream, sender) = NotificationStream::channel(cancellation_token.clone());
+
+        tokio::spawn(run_poll_loop(
+            self.oda.clone(),
+            self.datasets.clone(),
+            self.interval,
+            self.max_files,
+            self.store.clone(),
+            sender,
+            cancellation_token,
+        ));
+
+        stream
+    }
+}
+
+/// Files seen in the previous poll of a dataset.
+struct DatasetPoll {
+    dataset: DatasetConfig,
+    /// files created before this time existed when the poller started and are not reported
+    since: DateTime<Utc>,
+    seen: HashSet<String>,
+}
+
+impl DatasetPoll {
+    fn new(dataset: DatasetConfig, since: DateTime<Utc>) -> Self {
+        DatasetPoll {
+            dataset,
+            since,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Register the files of a poll and return the files which were not seen before, oldest
+    /// first. Files are new when created after `last_event_time`, or since the start when
+    /// there is no last event time.
+    fn new_files(
+        &mut self,
+        mut files: Vec<File>,
+        last_event_time: Option<DateTime<Utc>>,
+    ) -> Vec<File> {
+        let filenames = files.iter().map(|file| file.filename.clone()).collect();
+        let seen = std::mem::replace(&mut self.seen, filenames);
+        files.retain(|file| {
+            !seen.contains(&file.filename)
+                && created(file).is_some_and(|created| match last_event_time {
+                    Some(last_event_time) => created > last_event_time,
+                    None => created >= self.since,
+                })
+        });
+        files.sort_by(|a, b| a.created.cmp(&b.created));
+        files
+    }
+}
+
+fn created(file: &File) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&file.created)
+        .map(|created| created.with_timezone(&Utc))
+        .ok()
+}
+
+/// Create the notification the notification service sends for a new file.
+fn create_notification(
+    oda: &OpenDataAPI,
+    dataset: &DatasetConfig,
+    file: &File,
+) -> NotificationEvent {
+    let notification = NotificationReponse {
+        specversion: NOTIFICATION_SPECVERSION.to_string(),
+        notification_type: NOTIFICATION_TYPE.to_string(),
+        source: NOTIFICATION_SOURCE.to_string(),
+        id: format!(
+            "{}/{}/{}",
+            dataset.dataset_name, dataset.version, file.filename
+        ),
+        time: file.created.clone(),
+        datacontenttype: NOTIFICATION_CONTENT_TYPE.to_string(),
+        data: NotificationData {
+            dataset_name: dataset.dataset_name.clone(),
+            dataset_version: dataset.version.clone(),
+            filename: file.filename.clone(),
+            url: oda.get_file_download_url(dataset, &file.filename),
+        },
+    };
+
+    NotificationEvent::Notification {
+        topic: format!(
+            "{}/{}/{}/created",
+            TOPIC_BASE, dataset.dataset_name, dataset.version
+        ),
+        dataset: dataset.clone(),
+        notification,
+    }
+}
+
+async fn run_poll_loop(
+    oda: OpenDataAPI,
+    datasets: Vec<DatasetConfig>,
+    interval: Duration,
+    max_files: u32,
+    store: Option<Arc<NotificationStore>>,
+    sender: EventSender,
+    cancellation_token: CancellationToken,
+) {
+    let started = Utc::now();
+    let mut polls: Vec<DatasetPoll> = datasets
+        .into_iter()
+        .map(|dataset| DatasetPoll::new(dataset, started))
+        .collect();
+    let options = ListFilesOptions::latest(max_files);
+    let mut state = ConnectionState::Connecting;
+
+    while !cancellation_token.is_cancelled() {
+        let mut failed = false;
+
+        for poll in polls.iter_mut() {
+            let result = tokio::select! {
+                _ = cancellation_token.cancelled() => break,
+                result = oda.list_files(&poll.dataset, &options, None) => result,
+            };
+
+            let files = match result {
+                Ok(response) => response.files,
+                Err(e) => {
+                    failed = true;
+                    let error = NotificationError::PollError(e);
+                    if !sender.emit(NotificationEvent::Error(error)).await {
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            let last_event_time = store
+                .as_ref()
+                .and_then(|store| store.last_event_time(&poll.dataset));
+            for file in poll.new_files(files, last_event_time) {
+                let event = create_notification(&oda, &poll.dataset, &file);
+                if !sender.emit(event).await {
+                    return;
+                }
+            }
+        }
+
+        let next_state = if failed {
+            ConnectionState::Disconnected
+        } else {
+            ConnectionState::Subscribed
+        };
+        if next_state != state {
+            state = next_state;
+            if !sender.set_state(state).await {
+                return;
+            }
+        }
+
+        if !wait(&cancellation_token, interval).await {
+            break;
+        }
+    }
+
+    let _ = sender.set_state(ConnectionState::Stopped).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    /// Listing of the files, the minutes in the filename are added to the created time.
+    fn files_body(filenames: &[&str], created: DateTime<Utc>) -> String {
+        let files: Vec<serde_json::Value> = filenames
+            .iter()
+            .map(|filename| {
+                let minutes: i64 = filename[2..4].parse().unwrap();
+                let created = (created + chrono::Duration::minutes(minutes)).to_rfc3339();
+                serde_json::json!({
+                    "filename": filename,
+                    "size": 4,
+                    "created": created,
+                    "lastModified": created
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "isTruncated": false,
+            "resultCount": files.len(),
+            "files": files,
+            "maxResults": 100
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_new_files() {
+        let file = |filename: &str, created: &str| File {
+            filename: filename.to_string(),
+            size: 1,
+            created: created.to_string(),
+            last_modified: created.to_string(),
+        };
+        let since = DateTime::parse_from_rfc3339("2025-01-03T14:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut poll = DatasetPoll::new(
+            DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string()),
+            since,
+        );
+        let a = file("A", "2025-01-03T13:55:00+00:00");
+        let b = file("B", "2025-01-03T14:00:00+00:00");
+        let c = file("C", "2025-01-03T14:05:00+00:00");
+
+        // files created since the start are reported by the first successful poll
+        let new_files = poll.new_files(vec![c.clone(), b.clone(), a.clone()], None);
+        let filenames: Vec<&str> = new_files.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(filenames, vec!["B", "C"]);
+        assert!(poll
+            .new_files(vec![c.clone(), b.clone(), a.clone()], None)
+            .is_empty());
+
+        // with a last event time in the store files missed before the start are reported,
+        // files handled before are not
+        let mut poll = DatasetPoll::new(poll.dataset, since);
+        let last_event_time = DateTime::parse_from_rfc3339("2025-01-03T13:50:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let new_files = poll.new_files(vec![c.clone(), a.clone()], Some(last_event_time));
+        let filenames: Vec<&str> = new_files.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(filenames, vec!["A", "C"]);
+        let last_event_time = DateTime::parse_from_rfc3339("2025-01-03T14:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let new_files = poll.new_files(vec![c, b, a], Some(last_event_time));
+        assert!(new_files.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_poller_reports_files_created_before_the_first_successful_poll() {
+        let mut server = mockito::Server::new_async().await;
+        let path = "/datasets/radar_forecast/versions/2.0/files";
+        let failed = server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_status(403)
+            .create_async()
+            .await;
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        let dataset = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+        let poller = FilePoller::new(oda, vec![dataset]).with_interval(Duration::from_millis(50));
+        let mut stream = poller.stream();
+
+        assert!(matches!(
+            stream.next().await,
+            Some(NotificationEvent::Error(NotificationError::PollError(_)))
+        ));
+        failed.remove_async().await;
+
+        // created after the poller started but before the first successful poll
+        server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_body(files_body(&["RA05.h5"], Utc::now()))
+            .create_async()
+            .await;
+
+        loop {
+            match stream.next().await {
+                Some(NotificationEvent::Notification { notification, .. }) => {
+                    assert_eq!(notification.data.filename, "RA05.h5");
+                    break;
+                }
+                Some(NotificationEvent::Error(_)) | Some(NotificationEvent::StateChanged(_)) => {}
+                event => panic!("unexpected event: {:?}", event),
+            }
+        }
+        poller.stop();
+    }
+
+    #[tokio::test]
+    async fn test_poller_emits_notifications() {
+        let mut server = mockito::Server::new_async().await;
+        let path = "/datasets/radar_forecast/versions/2.0/files";
+        let first = server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_body(files_body(
+                &["RA00.h5"],
+                Utc::now() - chrono::Duration::hours(1),
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        let dataset = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+        let poller =
+            FilePoller::new(oda, vec![dataset.clone()]).with_interval(Duration::from_millis(50));
+        let mut stream = poller.stream();
+
+        assert!(matches!(
+            stream.next().await,
+            Some(NotificationEvent::StateChanged(ConnectionState::Subscribed))
+        ));
+        first.assert_async().await;
+
+        server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_body(files_body(&["RA10.h5", "RA05.h5", "RA00.h5"], Utc::now()))
+            .create_async()
+            .await;
+
+        let mut filenames = Vec::new();
+        while filenames.len() < 2 {
+            match stream.next().await {
+                Some(NotificationEvent::Notification {
+                    topic,
+                    dataset: notification_dataset,
+                    notification,
+                }) => {
+                    assert_eq!(topic, "dataplatform/file/v1/radar_forecast/2.0/created");
+                    assert_eq!(notification_dataset, dataset);
+                    assert_eq!(
+                        notification.data.url,
+                        format!(
+                            "{}{}/{}/url",
+                            server.url(),
+                            path,
+                            notification.data.filename
+                        )
+                    );
+                    filenames.push(notification.data.filename);
+                }
+                event => panic!("unexpected event: {:?}", event),
+            }
+        }
+        assert_eq!(filenames, vec!["RA05.h5", "RA10.h5"]);
+
+        poller.stop();
+        let mut last = None;
+        while let Some(event) = stream.next().await {
+            last = Some(event);
+        }
+        assert!(matches!(
+            last,
+            Some(NotificationEvent::StateChanged(ConnectionState::Stopped))
+        ));
+    }
+}
@@ -0,0 +1,562 @@
+use super::api::OpenDataAPI;
+use super::models::config::DatasetConfig;
+use super::models::options::{ListFilesOptions, OrderBy, Sorting};
+use super::models::response::{File, NotificationReponse};
+use crate::errors::{ApiError, NotificationError};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Default number of notification ids remembered by the store.
+pub const DEFAULT_STORE_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct StoreState {
+    /// acknowledged notification ids, oldest first
+    seen_ids: VecDeque<String>,
+    /// RFC 3339 time per dataset version up to which all received notifications were
+    /// acknowledged
+    last_event_times: HashMap<String, String>,
+    #[serde(skip)]
+    ids: HashSet<String>,
+    /// received notifications which are not acknowledged yet, id to dataset key and time,
+    /// the oldest notifications are given up on when the capacity is reached
+    #[serde(skip)]
+    pending: HashMap<String, (String, DateTime<Utc>)>,
+    /// times of acknowledged notifications per dataset version which are newer than an
+    /// older pending notification, these can not be used as last event time yet, the oldest
+    /// times are dropped when the capacity is reached
+    #[serde(skip)]
+    acknowledged_times: HashMap<String, Vec<DateTime<Utc>>>,
+    /// incremented on every change, used to skip writing an unchanged state
+    #[serde(skip)]
+    generation: u64,
+}
+
+impl StoreState {
+    /// Register a received notification as pending until it is acknowledged.
+    fn receive(&mut self, id: &str, dataset_key: String, time: DateTime<Utc>, capacity: usize) {
+        self.pending.insert(id.to_string(), (dataset_key, time));
+        while self.pending.len() > capacity {
+            let oldest = self
+                .pending
+                .iter()
+                .min_by_key(|(_, (_, time))| *time)
+                .map(|(id, _)| id.clone());
+            if let Some((dataset_key, _)) = oldest.and_then(|id| self.pending.remove(&id)) {
+                self.update_last_event_time(&dataset_key);
+                self.generation += 1;
+            }
+        }
+    }
+
+    /// Register the notification id as handled, returns false when it was handled before.
+    fn acknowledge(
+        &mut self,
+        id: &str,
+        dataset: &DatasetConfig,
+        time: &str,
+        capacity: usize,
+    ) -> bool {
+        self.pending.remove(id);
+        if !self.ids.insert(id.to_string()) {
+            return false;
+        }
+
+        self.seen_ids.push_back(id.to_string());
+        while self.seen_ids.len() > capacity {
+            if let Some(oldest) = self.seen_ids.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+
+        self.acknowledge_time(dataset, time, capacity);
+        true
+    }
+
+    /// Register the time of a handled notification or file of the dataset version.
+    fn acknowledge_time(&mut self, dataset: &DatasetConfig, time: &str, capacity: usize) {
+        if let Some(time) = parse_time(time) {
+            let dataset_key = dataset_key(dataset);
+            let times = self
+                .acknowledged_times
+                .entry(dataset_key.clone())
+                .or_default();
+            times.push(time);
+            if times.len() > capacity {
+                times.sort();
+                times.drain(..times.len() - capacity);
+            }
+            self.update_last_event_time(&dataset_key);
+        }
+
+        self.generation += 1;
+    }
+
+    /// Move the last event time of the dataset version to the newest acknowledged
+    /// notification which is older than all pending notifications.
+    fn update_last_event_time(&mut self, dataset_key: &str) {
+        let oldest_pending = self
+            .pending
+            .values()
+            .filter(|(key, _)| key == dataset_key)
+            .map(|(_, time)| *time)
+            .min();
+        let times = self
+            .acknowledged_times
+            .entry(dataset_key.to_string())
+            .or_default();
+        let (ready, waiting): (Vec<DateTime<Utc>>, Vec<DateTime<Utc>>) = times
+            .iter()
+            .partition(|time| oldest_pending.is_none_or(|pending| **time < pending));
+        *times = waiting;
+
+        let last_time = self
+            .last_event_times
+            .get(dataset_key)
+            .and_then(|time| parse_time(time));
+        if let Some(time) = ready.into_iter().max() {
+            if last_time.is_none_or(|last_time| time > last_time) {
+                self.last_event_times
+                    .insert(dataset_key.to_string(), time.to_rfc3339());
+            }
+        }
+    }
+}
+
+/// Store of handled notifications used to suppress duplicate notifications, for example
+/// messages redelivered by the broker after a reconnect with `clean_session=false`.
+/// The store keeps the last `capacity` notification ids and the time of the last
+/// notification per dataset version.
+///
+/// A notification is only stored once the consumer acknowledges it with `acknowledge`,
+/// for example after the file was downloaded. Notifications which are received but not
+/// acknowledged are not suppressed when the broker delivers them again, which depends on
+/// the session of the broker and not on the store. They hold back the time of the last
+/// notification, so the files are also returned by `OpenDataAPI::list_missed_files`. Of the
+/// pending notifications only the last `capacity` are kept.
+///
+/// A store opened from a file is written on every acknowledgement so duplicates are also
+/// suppressed across restarts, writes are done on the blocking thread pool and
+/// acknowledgements made while a write is in progress are written together.
+///
+/// # Example
+/// ```no_run
+/// use kanemi::dataplatform::api::OpenDataAPI;
+/// use kanemi::dataplatform::models::config::{KnownDataset, MqttConfig};
+/// use kanemi::dataplatform::notification::NotificationService;
+/// use kanemi::dataplatform::pipeline::DownloadPipeline;
+/// use kanemi::dataplatform::store::NotificationStore;
+/// use std::sync::Arc;
+///
+/// # async fn example() {
+/// let store = Arc::new(NotificationStore::open("./notifications.json").unwrap());
+/// let config = MqttConfig::new_default(
+///     "api-key".to_string(),
+///     KnownDataset::NowcastPrecipitation.config(),
+/// );
+/// let service = NotificationService::new(config).with_store(store.clone());
+///
+/// // the pipeline acknowledges every notification after the file is downloaded
+/// let pipeline = DownloadPipeline::new(OpenDataAPI::new("api-key".to_string()), "./output".to_string())
+///     .with_store(store);
+/// pipeline.run(service.stream("client-id".to_string(), false)).await;
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct NotificationStore {
+    path: Option<PathBuf>,
+    capacity: usize,
+    state: Mutex<StoreState>,
+    /// generation of the state in the file, held while writing
+    written_generation: tokio::sync::Mutex<u64>,
+}
+
+impl NotificationStore {
+    /// Store which is not persisted, duplicates are only suppressed while running.
+    pub fn in_memory() -> Self {
+        NotificationStore {
+            path: None,
+            capacity: DEFAULT_STORE_CAPACITY,
+            state: Mutex::new(StoreState::default()),
+            written_generation: tokio::sync::Mutex::new(0),
+        }
+    }
+
+    /// Open a store persisted in the JSON file at the given path, the file is created on
+    /// the first acknowledgement when it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, NotificationError> {
+        let path = path.as_ref().to_path_buf();
+        let mut state = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str::<StoreState>(&content).map_err(|e| {
+                NotificationError::StoreError(format!("Failed to parse {}: {}", path.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => StoreState::default(),
+            Err(e) => {
+                return Err(NotificationError::StoreError(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        state.ids = state.seen_ids.iter().cloned().collect();
+
+        Ok(NotificationStore {
+            path: Some(path),
+            capacity: DEFAULT_STORE_CAPACITY,
+            state: Mutex::new(state),
+            written_generation: tokio::sync::Mutex::new(0),
+        })
+    }
+
+    /// Set the max number of notification ids to remember, the oldest ids are forgotten.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Check if a notification with the id was acknowledged before.
+    pub fn contains(&self, id: &str) -> bool {
+        self.state.lock().unwrap().ids.contains(id)
+    }
+
+    /// Time of the last notification of the dataset version which was acknowledged, while
+    /// all older notifications were acknowledged as well. None when no notification was
+    /// acknowledged or the time could not be parsed.
+    pub fn last_event_time(&self, dataset: &DatasetConfig) -> Option<DateTime<Utc>> {
+        self.state
+            .lock()
+            .unwrap()
+            .last_event_times
+            .get(&dataset_key(dataset))
+            .and_then(|time| parse_time(time))
+    }
+
+    /// Register a received notification which is not handled yet, returns false when the
+    /// notification was acknowledged before. Used by `NotificationService`, the time of the
+    /// last notification does not move past the notification until it is acknowledged.
+    pub fn receive(&self, notification: &NotificationReponse) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.ids.contains(&notification.id) {
+            return false;
+        }
+
+        if let Some(time) = parse_time(&notification.time) {
+            let dataset_key = dataset_key(&notification.data.dataset_config());
+            state.receive(&notification.id, dataset_key, time, self.capacity);
+        }
+
+        true
+    }
+
+    /// Register the notification as handled, returns false when the notification was
+    /// acknowledged before. A persisted store writes the file before returning.
+    ///
+    /// # Errors
+    /// - `NotificationError::StoreError`: If the store can not be written.
+    pub async fn acknowledge(
+        &self,
+        notification: &NotificationReponse,
+    ) -> Result<bool, NotificationError> {
+        let acknowledged = self.state.lock().unwrap().acknowledge(
+            &notification.id,
+            &notification.data.dataset_config(),
+            &notification.time,
+            self.capacity,
+        );
+        if acknowledged {
+            self.persist().await?;
+        }
+
+        Ok(acknowledged)
+    }
+
+    /// Register a file of the dataset version as handled without a notification, for
+    /// example a file returned by `OpenDataAPI::list_missed_files` which was downloaded.
+    /// Only the time of the last notification is updated, there is no notification id.
+    ///
+    /// # Errors
+    /// - `NotificationError::StoreError`: If the store can not be written.
+    pub async fn acknowledge_file(
+        &self,
+        dataset: &DatasetConfig,
+        file: &File,
+    ) -> Result<(), NotificationError> {
+        self.state
+            .lock()
+            .unwrap()
+            .acknowledge_time(dataset, &file.created, self.capacity);
+        self.persist().await
+    }
+
+    /// Write the state to the file when it changed since the last write. The write lock
+    /// is held while writing, waiting acknowledgements are written with the next write.
+    async fn persist(&self) -> Result<(), NotificationError> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => return Ok(()),
+        };
+
+        let mut written_generation = self.written_generation.lock().await;
+        let (generation, content) = {
+            let state = self.state.lock().unwrap();
+            if state.generation == *written_generation {
+                return Ok(());
+            }
+
+            let content = serde_json::to_string(&*state).map_err(|e| {
+                NotificationError::StoreError(format!("Failed to write {}: {}", path.display(), e))
+            })?;
+            (state.generation, content)
+        };
+
+        tokio::task::spawn_blocking(move || write_file(&path, content.as_bytes()))
+            .await
+            .map_err(|e| NotificationError::StoreError(e.to_string()))??;
+        *written_generation = generation;
+        Ok(())
+    }
+}
+
+/// Write to a temporary file first and rename it, so the store is not corrupted when
+/// interrupted. The temporary file is synced to disk before the rename.
+fn write_file(path: &Path, content: &[u8]) -> Result<(), NotificationError> {
+    let store_error = |e: std::io::Error| {
+        NotificationError::StoreError(format!("Failed to write {}: {}", path.display(), e))
+    };
+
+    let mut temp_path = path.to_path_buf().into_os_string();
+    temp_path.push(".tmp");
+    let mut file = std::fs::File::create(&temp_path).map_err(store_error)?;
+    file.write_all(content).map_err(store_error)?;
+    file.sync_all().map_err(store_error)?;
+    drop(file);
+
+    std::fs::rename(&temp_path, path).map_err(store_error)
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+}
+
+fn dataset_key(dataset: &DatasetConfig) -> String {
+    format!("{}/{}", dataset.dataset_name, dataset.version)
+}
+
+impl OpenDataAPI {
+    /// List the files of the dataset version created after the last notification in the
+    /// store, oldest first. These are the files which were missed or not handled while
+    /// listening for notifications. Files of notifications which were acknowledged after a
+    /// notification which was not can be listed again. Returns no files when the store has
+    /// no notification of the dataset.
+    pub async fn list_missed_files(
+        &self,
+        dataset: &DatasetConfig,
+        store: &NotificationStore,
+    ) -> Result<Vec<File>, ApiError> {
+        let since = match store.last_event_time(dataset) {
+            Some(since) => since,
+            None => return Ok(Vec::new()),
+        };
+
+        let options = ListFilesOptions {
+            order_by: Some(OrderBy::Created),
+            sorting: Some(Sorting::Asc),
+            begin: Some(since),
+            ..Default::default()
+        };
+
+        let files: Vec<File> = self.list_all_files(dataset, options).try_collect().await?;
+
+        Ok(files
+            .into_iter()
+            .filter(|file| parse_time(&file.created).is_none_or(|created| created > since))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataplatform::models::response::NotificationData;
+
+    fn notification(id: &str, time: &str) -> NotificationReponse {
+        NotificationReponse {
+            specversion: "1.0".to_string(),
+            notification_type: "nl.knmi.dataplatform.file.created.v1".to_string(),
+            source: "https://dataplatform.knmi.nl".to_string(),
+            id: id.to_string(),
+            time: time.to_string(),
+            datacontenttype: "application/json".to_string(),
+            data: NotificationData {
+                dataset_name: "radar_forecast".to_string(),
+                dataset_version: "2.0".to_string(),
+                filename: format!("{}.h5", id),
+                url: "https://example.com".to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_persists_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.json");
+        let dataset = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+
+        let store = NotificationStore::open(&path).unwrap().with_capacity(2);
+        assert!(store.receive(&notification("1", "2025-01-03T14:55:00Z")));
+        // received notifications are not stored until acknowledged
+        assert!(!path.exists());
+        assert!(store
+            .acknowledge(&notification("1", "2025-01-03T14:55:00Z"))
+            .await
+            .unwrap());
+        assert!(!store.receive(&notification("1", "2025-01-03T14:55:00Z")));
+        assert!(!store
+            .acknowledge(&notification("1", "2025-01-03T14:55:00Z"))
+            .await
+            .unwrap());
+        assert!(store
+            .acknowledge(&notification("2", "2025-01-03T15:00:00Z"))
+            .await
+            .unwrap());
+        assert!(store
+            .acknowledge(&notification("3", "2025-01-03T14:50:00Z"))
+            .await
+            .unwrap());
+        drop(store);
+
+        let store = NotificationStore::open(&path).unwrap().with_capacity(2);
+        // the oldest id is forgotten
+        assert!(!store.contains("1"));
+        assert!(store.contains("2"));
+        assert!(!store
+            .acknowledge(&notification("3", "2025-01-03T14:50:00Z"))
+            .await
+            .unwrap());
+        assert_eq!(
+            store.last_event_time(&dataset).unwrap().to_rfc3339(),
+            "2025-01-03T15:00:00+00:00"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pending_notifications_hold_back_last_event_time() {
+        let dataset = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+        let store = NotificationStore::in_memory();
+
+        assert!(store.receive(&notification("1", "2025-01-03T14:55:00Z")));
+        assert!(store.receive(&notification("2", "2025-01-03T15:00:00Z")));
+        assert!(store.receive(&notification("3", "2025-01-03T15:05:00Z")));
+        store
+            .acknowledge(&notification("1", "2025-01-03T14:55:00Z"))
+            .await
+            .unwrap();
+        // the handler of notification 2 failed, the later notification 3 succeeded
+        store
+            .acknowledge(&notification("3", "2025-01-03T15:05:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.last_event_time(&dataset).unwrap().to_rfc3339(),
+            "2025-01-03T14:55:00+00:00"
+        );
+
+        store
+            .acknowledge(&notification("2", "2025-01-03T15:00:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.last_event_time(&dataset).unwrap().to_rfc3339(),
+            "2025-01-03T15:05:00+00:00"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pending_notifications_are_bounded() {
+        let dataset = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+        let store = NotificationStore::in_memory().with_capacity(2);
+
+        assert!(store.receive(&notification("1", "2025-01-03T14:55:00Z")));
+        assert!(store.receive(&notification("2", "2025-01-03T15:00:00Z")));
+        // the oldest pending notification is given up on
+        assert!(store.receive(&notification("3", "2025-01-03T15:05:00Z")));
+        assert_eq!(store.state.lock().unwrap().pending.len(), 2);
+
+        store
+            .acknowledge(&notification("3", "2025-01-03T15:05:00Z"))
+            .await
+            .unwrap();
+        store
+            .acknowledge(&notification("2", "2025-01-03T15:00:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.last_event_time(&dataset).unwrap().to_rfc3339(),
+            "2025-01-03T15:05:00+00:00"
+        );
+        assert!(store.state.lock().unwrap().pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_missed_files() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/datasets/radar_forecast/versions/2.0/files")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "begin".to_string(),
+                "2025-01-03T15:00:00Z".to_string(),
+            ))
+            .with_body(
+                serde_json::json!({
+                    "isTruncated": false,
+                    "resultCount": 3,
+                    "files": [
+                        {"filename": "A.h5", "size": 1, "created": "2025-01-03T15:00:00+00:00", "lastModified": "2025-01-03T15:00:00+00:00"},
+                        {"filename": "B.h5", "size": 1, "created": "2025-01-03T15:05:00+00:00", "lastModified": "2025-01-03T15:05:00+00:00"},
+                        {"filename": "C.h5", "size": 1, "created": "2025-01-03T15:10:00+00:00", "lastModified": "2025-01-03T15:10:00+00:00"}
+                    ],
+                    "maxResults": 500
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        let dataset = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+        let store = NotificationStore::in_memory();
+        assert!(oda
+            .list_missed_files(&dataset, &store)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // B is received but not handled, C is handled after B
+        store.receive(&notification("B", "2025-01-03T15:05:00Z"));
+        store
+            .acknowledge(&notification("A", "2025-01-03T15:00:00Z"))
+            .await
+            .unwrap();
+        store
+            .acknowledge(&notification("C", "2025-01-03T15:10:00Z"))
+            .await
+            .unwrap();
+        let missed = oda.list_missed_files(&dataset, &store).await.unwrap();
+        let filenames: Vec<&str> = missed.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(filenames, vec!["B.h5", "C.h5"]);
+        mock.assert_async().await;
+    }
+}
@@ -0,0 +1,344 @@
+use super::api::{clean_stale_partial_files, OpenDataAPI, PARTIAL_FILE_EXTENSION};
+use super::models::config::{DatasetConfig, KnownDataset};
+use super::models::response::File as DatasetFile;
+use crate::errors::ApiError;
+use crate::utils::timestamp_from_filename;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tokio::fs;
+
+/// Options for keeping a local directory in sync with a dataset version.
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    /// files with a timestamp in their filename within this window before now are kept in sync
+    pub window: Duration,
+    /// remove local files with a timestamp older than the window, only supported for known
+    /// datasets as their files are recognised by the filename pattern
+    pub prune: bool,
+    /// max number of concurrent downloads
+    pub concurrency: usize,
+}
+
+impl SyncOptions {
+    pub fn new(window: Duration) -> Self {
+        SyncOptions {
+            window,
+            prune: false,
+            concurrency: super::api::DEFAULT_DOWNLOAD_CONCURRENCY,
+        }
+    }
+}
+
+/// A file which could not be downloaded during a sync.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncFailure {
+    pub filename: String,
+    pub error: String,
+}
+
+/// Changes made to the local directory by a sync, all paths are local file paths.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncReport {
+    /// files which were missing or incomplete and have been downloaded
+    pub downloaded: Vec<String>,
+    /// files which were already up to date
+    pub unchanged: Vec<String>,
+    /// expired files which have been removed
+    pub pruned: Vec<String>,
+    /// files which could not be downloaded
+    pub failed: Vec<SyncFailure>,
+}
+
+impl SyncReport {
+    /// True when all files in the window are available locally.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl OpenDataAPI {
+    /// Synchronize the directory with the files of the dataset version within the window.
+    /// Files which are missing locally or differ in size from the remote file are downloaded,
+    /// when `prune` is set local files of the dataset with a timestamp older than the window are
+    /// removed. Pruning requires a known dataset, its files are recognised by the filename pattern.
+    /// A file which fails to download does not abort the sync but is listed in the report.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kanemi::dataplatform::api::OpenDataAPI;
+    /// use kanemi::dataplatform::models::config::KnownDataset;
+    /// use kanemi::dataplatform::sync::SyncOptions;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), kanemi::errors::ApiError> {
+    /// let oda = OpenDataAPI::new("api-key".to_string());
+    /// let options = SyncOptions {
+    ///     prune: true,
+    ///     ..SyncOptions::new(Duration::from_secs(24 * 60 * 60))
+    /// };
+    /// let report = oda
+    ///     .sync_directory(&KnownDataset::ActualSynopticObservations.config(), "./output", &options)
+    ///     .await?;
+    /// println!("{}", serde_json::to_string(&report).unwrap());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// - `ApiError::ConfigurationError`: If the window is out of range or `prune` is set for a
+    ///   dataset which is not a `KnownDataset`.
+    /// - `ApiError::SaveFileError`: If the directory can not be read or a file can not be removed.
+    /// - Any error returned while listing the remote files.
+    pub async fn sync_directory(
+        &self,
+        dataset: &DatasetConfig,
+        dir: &str,
+        options: &SyncOptions,
+    ) -> Result<SyncReport, ApiError> {
+        let window = chrono::Duration::from_std(options.window)
+            .map_err(|e| ApiError::ConfigurationError(e.to_string()))?;
+        let end = Utc::now();
+        let begin = end - window;
+
+        let known_dataset = KnownDataset::from_config(dataset);
+        if options.prune && known_dataset.is_none() {
+            return Err(ApiError::ConfigurationError(format!(
+                "Pruning is only supported for known datasets, not for {} {}",
+                dataset.dataset_name, dataset.version
+            )));
+        }
+
+        fs::create_dir_all(dir)
+            .await
+            .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
+        clean_stale_partial_files(dir).await?;
+
+        let remote_files = self.get_files_between(dataset, begin, end).await?;
+        let local_files = list_local_files(dir).await?;
+
+        let mut report = SyncReport::default();
+        let mut missing: Vec<DatasetFile> = Vec::new();
+        for file in remote_files {
+            match local_files.get(&file.filename) {
+                Some(size) if *size == file.size => {
+                    report.unchanged.push(local_path(dir, &file.filename))
+                }
+                _ => missing.push(file),
+            }
+        }
+
+        let results: Vec<(DatasetFile, Result<String, ApiError>)> = stream::iter(missing)
+            .map(|file| async move {
+                let result = self
+                    .download_dataset_file(dataset, &file, dir, Some(true))
+                    .await;
+                (file, result)
+            })
+            .buffered(options.concurrency.max(1))
+            .collect()
+            .await;
+
+        for (file, result) in results {
+            match result {
+                Ok(path) => report.downloaded.push(path),
+                Err(e) => report.failed.push(SyncFailure {
+                    filename: file.filename,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        if let (true, Some(known_dataset)) = (options.prune, known_dataset) {
+            let filenames = local_files
+                .keys()
+                .filter(|filename| known_dataset.matches_filename(filename));
+            report.pruned = prune_expired_files(dir, filenames, begin).await?;
+        }
+
+        Ok(report)
+    }
+}
+
+fn local_path(dir: &str, filename: &str) -> String {
+    Path::new(dir).join(filename).to_string_lossy().to_string()
+}
+
+/// List the complete files in the directory with their size.
+async fn list_local_files(dir: &str) -> Result<HashMap<String, u64>, ApiError> {
+    let mut files = HashMap::new();
+    let mut entries = fs::read_dir(dir)
+        .await
+        .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| ApiError::SaveFileError(e.to_string()))?
+    {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) == Some(PARTIAL_FILE_EXTENSION) {
+            continue;
+        }
+
+        if let Ok(metadata) = entry.metadata().await {
+            if metadata.is_file() {
+                files.insert(
+                    entry.file_name().to_string_lossy().to_string(),
+                    metadata.len(),
+                );
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Remove the files with a timestamp in their filename before `oldest`, files without a
+/// timestamp are left alone. The caller limits `filenames` to the files of the synced
+/// dataset. Returns the paths of the removed files.
+async fn prune_expired_files<'a>(
+    dir: &str,
+    filenames: impl Iterator<Item = &'a String>,
+    oldest: DateTime<Utc>,
+) -> Result<Vec<String>, ApiError> {
+    let mut pruned = Vec::new();
+    for filename in filenames {
+        let expired = timestamp_from_filename(filename)
+            .map(|t| t.and_utc() < oldest)
+            .unwrap_or(false);
+        if !expired {
+            continue;
+        }
+
+        let path = local_path(dir, filename);
+        fs::remove_file(&path)
+            .await
+            .map_err(|e| ApiError::SaveFileError(e.to_string()))?;
+        pruned.push(path);
+    }
+
+    pruned.sort();
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    fn filename_at(timestamp: DateTime<Utc>) -> String {
+        format!("RAD_NL25_RAC_FM_{}.h5", timestamp.format("%Y%m%d%H%M"))
+    }
+
+    #[tokio::test]
+    async fn test_sync_directory() {
+        let now = Utc::now();
+        let present = filename_at(now - chrono::Duration::hours(2));
+        let missing = filename_at(now - chrono::Duration::hours(1));
+        let expired = filename_at(now - chrono::Duration::hours(30));
+
+        let mut server = mockito::Server::new_async().await;
+        let files: Vec<serde_json::Value> = [&present, &missing]
+            .iter()
+            .map(|filename| {
+                serde_json::json!({
+                    "filename": filename,
+                    "size": 4,
+                    "created": now.to_rfc3339(),
+                    "lastModified": now.to_rfc3339()
+                })
+            })
+            .collect();
+        let listing = server
+            .mock("GET", "/datasets/radar_forecast/versions/2.0/files")
+            .match_query(Matcher::Any)
+            .with_body(
+                serde_json::json!({
+                    "isTruncated": false,
+                    "resultCount": 2,
+                    "files": files,
+                    "maxResults": 500
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let url = server
+            .mock(
+                "GET",
+                format!(
+                    "/datasets/radar_forecast/versions/2.0/files/{}/url",
+                    missing
+                )
+                .as_str(),
+            )
+            .with_body(
+                serde_json::json!({
+                    "contentType": "application/x-hdf5",
+                    "lastModified": now.to_rfc3339(),
+                    "size": "4",
+                    "temporaryDownloadUrl": format!("{}/download", server.url())
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let download = server
+            .mock("GET", "/download")
+            .with_body("0123")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir_path = dir.path().to_str().unwrap();
+        std::fs::write(dir.path().join(&present), b"0123").unwrap();
+        std::fs::write(dir.path().join(&expired), b"0123").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"0123").unwrap();
+        let other_dataset = format!(
+            "KMDS__OPER_P___10M_OBS_L2_{}.nc",
+            (now - chrono::Duration::days(3)).format("%Y%m%d%H%M")
+        );
+        std::fs::write(dir.path().join(&other_dataset), b"0123").unwrap();
+
+        let oda = OpenDataAPI::builder()
+            .api_key("key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        let dataset = DatasetConfig::new("radar_forecast".to_string(), "2.0".to_string());
+        let options = SyncOptions {
+            prune: true,
+            ..SyncOptions::new(Duration::from_secs(24 * 60 * 60))
+        };
+
+        let report = oda
+            .sync_directory(&dataset, dir_path, &options)
+            .await
+            .unwrap();
+        assert_eq!(report.downloaded, vec![local_path(dir_path, &missing)]);
+        assert_eq!(report.unchanged, vec![local_path(dir_path, &present)]);
+        assert_eq!(report.pruned, vec![local_path(dir_path, &expired)]);
+        assert!(report.is_complete());
+        assert!(dir.path().join("notes.txt").exists());
+        assert!(dir.path().join(&other_dataset).exists());
+
+        listing.assert_async().await;
+        url.assert_async().await;
+        download.assert_async().await;
+
+        // files of an unknown dataset can not be recognised for pruning
+        let unknown = DatasetConfig::new("unknown".to_string(), "1".to_string());
+        assert!(matches!(
+            oda.sync_directory(&unknown, dir_path, &options)
+                .await
+                .unwrap_err(),
+            ApiError::ConfigurationError(_)
+        ));
+    }
+}
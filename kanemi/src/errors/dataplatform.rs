@@ -1,4 +1,45 @@
+use crate::dataplatform::models::response::ErrorResponse;
 use std::fmt;
+use std::time::Duration;
+
+/// A non successful HTTP response from the Open Data API.
+#[derive(Debug)]
+pub struct HttpStatusError {
+    /// HTTP status code of the response
+    pub status: u16,
+    /// Parsed KNMI error body, None if the body could not be parsed
+    pub body: Option<ErrorResponse>,
+}
+
+impl fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.body.as_ref().and_then(|b| b.description()) {
+            Some(description) => write!(f, "HTTP {}: {}", self.status, description),
+            None => write!(f, "HTTP {}", self.status),
+        }
+    }
+}
+
+/// Connecting to the MQTT broker failed or the connection was lost.
+#[derive(Debug)]
+pub struct ConnectionFailure {
+    /// Error returned by the MQTT client
+    pub cause: Box<rumqttc::ConnectionError>,
+    /// Number of consecutive failed attempts
+    pub attempt: u32,
+    /// Delay before the next attempt, None when no more attempts are made
+    pub retry_in: Option<Duration>,
+}
+
+impl fmt::Display for ConnectionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "attempt {}: {}", self.attempt, self.cause)?;
+        match self.retry_in {
+            Some(delay) => write!(f, ", retrying in {:.1} seconds", delay.as_secs_f64()),
+            None => write!(f, ", giving up"),
+        }
+    }
+}
 
 /// Errors that can occur within the API
 #[derive(Debug)]
@@ -15,11 +56,66 @@ pub enum ApiError {
     /// An error occurred during parsing the notification response.
     NotificationResponseParseError(String),
 
+    /// An error occurred during parsing the dataset metadata response.
+    MetadataResponseParseError(String),
+
     /// Missing or invalid configuration.
     ConfigurationError(String),
 
     /// An error occurred during saving the file.
     SaveFileError(String),
+
+    /// The downloaded file could not be opened as a dataset.
+    DatasetLoadError(String),
+
+    /// An error occurred reading or updating the local dataset cache.
+    CacheError(String),
+
+    /// No file found matching the request.
+    FileNotFound(String),
+
+    /// The filename is not a plain file name, for example it contains a directory.
+    InvalidFilename(String),
+
+    /// The downloaded file did not pass verification.
+    VerificationError(String),
+
+    /// Too many requests, the rate limit of the API key is exceeded (429).
+    RateLimitExceeded {
+        error: HttpStatusError,
+        /// Delay requested by the Retry-After header of the response
+        retry_after: Option<Duration>,
+    },
+
+    /// The API key is missing or invalid (401).
+    Unauthorized(HttpStatusError),
+
+    /// The API key has no access to the requested resource (403).
+    Forbidden(HttpStatusError),
+
+    /// The requested dataset, version or file does not exist (404).
+    NotFound(HttpStatusError),
+
+    /// The server failed to handle the request (5xx).
+    ServerError(HttpStatusError),
+
+    /// Any other non successful HTTP response.
+    UnexpectedStatus(HttpStatusError),
+}
+
+impl ApiError {
+    /// Returns the HTTP status code if the error was caused by a non successful response.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ApiError::Unauthorized(e)
+            | ApiError::Forbidden(e)
+            | ApiError::NotFound(e)
+            | ApiError::ServerError(e)
+            | ApiError::UnexpectedStatus(e)
+            | ApiError::RateLimitExceeded { error: e, .. } => Some(e.status),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for ApiError {
@@ -31,8 +127,40 @@ impl fmt::Display for ApiError {
             ApiError::NotificationResponseParseError(s) => {
                 write!(f, "Notification response parse error: {}", s)
             }
+            ApiError::MetadataResponseParseError(s) => {
+                write!(f, "Metadata response parse error: {}", s)
+            }
             ApiError::ConfigurationError(s) => write!(f, "Configuration error: {}", s),
             ApiError::SaveFileError(s) => write!(f, "Error saving file: {}", s),
+            ApiError::DatasetLoadError(s) => write!(f, "Error loading dataset: {}", s),
+            ApiError::CacheError(s) => write!(f, "Cache error: {}", s),
+            ApiError::FileNotFound(s) => write!(f, "File not found: {}", s),
+            ApiError::InvalidFilename(s) => write!(f, "Invalid filename: {}", s),
+            ApiError::VerificationError(s) => write!(f, "Verification error: {}", s),
+            ApiError::RateLimitExceeded { error, retry_after } => match retry_after {
+                Some(retry_after) => write!(
+                    f,
+                    "Rate limit exceeded, retry after {} seconds ({})",
+                    retry_after.as_secs(),
+                    error
+                ),
+                None => write!(f, "Rate limit exceeded ({})", error),
+            },
+            ApiError::Unauthorized(e) => {
+                write!(f, "Unauthorized, the API key is missing or invalid ({})", e)
+            }
+            ApiError::Forbidden(e) => write!(
+                f,
+                "Forbidden, the API key has no access to the requested resource ({})",
+                e
+            ),
+            ApiError::NotFound(e) => write!(
+                f,
+                "Not found, the dataset, dataset version or file does not exist ({})",
+                e
+            ),
+            ApiError::ServerError(e) => write!(f, "Server error ({})", e),
+            ApiError::UnexpectedStatus(e) => write!(f, "Unexpected response ({})", e),
         }
     }
 }
@@ -42,15 +170,40 @@ pub enum NotificationError {
     /// Connection was lost
     ConnectionError(String),
 
+    /// Connecting failed or the connection was lost, carries the underlying cause
+    ConnectionFailed(ConnectionFailure),
+
     /// Subscription error
     SubscriptionError(String),
+
+    /// A received message could not be parsed as notification
+    ParseError(String),
+
+    /// Polling the file listing of the Open Data API failed
+    PollError(ApiError),
+
+    /// Reading or writing the notification store failed
+    StoreError(String),
 }
 
 impl fmt::Display for NotificationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             NotificationError::ConnectionError(s) => write!(f, "Connection error: {}", s),
+            NotificationError::ConnectionFailed(e) => write!(f, "Connection failed, {}", e),
             NotificationError::SubscriptionError(s) => write!(f, "Subscription error: {}", s),
+            NotificationError::ParseError(s) => write!(f, "Notification parse error: {}", s),
+            NotificationError::PollError(e) => write!(f, "Poll error: {}", e),
+            NotificationError::StoreError(s) => write!(f, "Store error: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for NotificationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NotificationError::ConnectionFailed(e) => Some(e.cause.as_ref()),
+            _ => None,
         }
     }
 }
@@ -11,6 +11,12 @@ pub enum DatasetError {
     ReadError(String),
     /// Image index is out of bounds.
     ImageIndexOutOfBounds(String),
+    /// The file does not have the expected format, for example a missing attribute.
+    FormatError(String),
+    /// The area is not valid, for example invalid GeoJSON.
+    InvalidArea(String),
+    /// An error occurred while writing an output file.
+    WriteError(String),
 }
 
 impl fmt::Display for DatasetError {
@@ -19,11 +25,10 @@ impl fmt::Display for DatasetError {
             DatasetError::OutOfBounds(s) => write!(f, "Out of bounds: {}", s),
             DatasetError::FileNotFound(s) => write!(f, "File not found: {}", s),
             DatasetError::ReadError(s) => write!(f, "Read error: {}", s),
-            DatasetError::ImageIndexOutOfBounds(s) => write!(
-                f,
-                "Image index out of bounds, should be between 1 and 25: {}",
-                s
-            ),
+            DatasetError::ImageIndexOutOfBounds(s) => write!(f, "Image index out of bounds: {}", s),
+            DatasetError::FormatError(s) => write!(f, "Format error: {}", s),
+            DatasetError::InvalidArea(s) => write!(f, "Invalid area: {}", s),
+            DatasetError::WriteError(s) => write!(f, "Write error: {}", s),
         }
     }
 }
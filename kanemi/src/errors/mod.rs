@@ -5,6 +5,8 @@ mod projection;
 
 pub use cy43p1::CY43P1Error;
 pub use dataplatform::ApiError;
+pub use dataplatform::ConnectionFailure;
+pub use dataplatform::HttpStatusError;
 pub use dataplatform::NotificationError;
 pub use dataset::DatasetError;
 pub use projection::ProjectionError;
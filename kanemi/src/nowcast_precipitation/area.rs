@@ -0,0 +1,392 @@
+//! Areas used to aggregate the nowcast precipitation over multiple pixels.
+
+use super::grid::GridDefinition;
+use crate::errors::DatasetError;
+use serde_json::Value;
+
+/// Number of points inserted on every edge of a bounding box, lines of constant longitude
+/// or latitude are curved in the grid projection.
+const BBOX_EDGE_POINTS: usize = 32;
+
+/// Area on the nowcast grid in EPSG:4326.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Area {
+    /// Bounding box in longitude and latitude.
+    BoundingBox {
+        min_lon: f64,
+        min_lat: f64,
+        max_lon: f64,
+        max_lat: f64,
+    },
+    /// One or more polygons, every polygon is a list of rings of (longitude, latitude)
+    /// points. The first ring is the exterior, the other rings are holes.
+    Polygons(Vec<Vec<Vec<(f64, f64)>>>),
+}
+
+impl Area {
+    /// Creates a bounding box area from the min and max longitude and latitude.
+    ///
+    /// # Errors
+    /// - `DatasetError::InvalidArea`: If a min value is larger than the max value.
+    pub fn bbox(
+        min_lon: f64,
+        min_lat: f64,
+        max_lon: f64,
+        max_lat: f64,
+    ) -> Result<Area, DatasetError> {
+        if !(min_lon <= max_lon && min_lat <= max_lat) {
+            return Err(DatasetError::InvalidArea(format!(
+                "Invalid bounding box: {}, {}, {}, {}",
+                min_lon, min_lat, max_lon, max_lat
+            )));
+        }
+
+        Ok(Area::BoundingBox {
+            min_lon,
+            min_lat,
+            max_lon,
+            max_lat,
+        })
+    }
+
+    /// Parses an area from GeoJSON, supported are a Polygon or MultiPolygon geometry and
+    /// a Feature or FeatureCollection containing these geometries.
+    ///
+    /// # Errors
+    /// - `DatasetError::InvalidArea`: If the GeoJSON is invalid or contains no polygons.
+    ///
+    /// # Example
+    /// ```
+    /// use kanemi::nowcast_precipitation::area::Area;
+    /// let area = Area::from_geojson(
+    ///     r#"{"type": "Polygon", "coordinates": [[[5.0, 52.0], [5.1, 52.0], [5.1, 52.1], [5.0, 52.0]]]}"#,
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn from_geojson(geojson: &str) -> Result<Area, DatasetError> {
+        let value: Value = serde_json::from_str(geojson)
+            .map_err(|e| DatasetError::InvalidArea(format!("Invalid GeoJSON: {}", e)))?;
+
+        let mut polygons = Vec::new();
+        collect_polygons(&value, &mut polygons)?;
+        if polygons.is_empty() {
+            return Err(DatasetError::InvalidArea(
+                "GeoJSON does not contain a polygon".to_string(),
+            ));
+        }
+
+        Ok(Area::Polygons(polygons))
+    }
+
+    /// Returns the (column, row) positions of the grid pixels with their center inside the
+    /// area. An area smaller than a pixel returns the pixel at the center of the area.
+    ///
+    /// # Errors
+    /// - `DatasetError::OutOfBounds`: If the area is outside the grid.
+    /// - `DatasetError::InvalidArea`: If the area can not be projected to the grid.
+    pub fn grid_positions(
+        &self,
+        grid: &GridDefinition,
+    ) -> Result<Vec<(usize, usize)>, DatasetError> {
+        let polygons = self
+            .polygons()
+            .iter()
+            .map(|rings| {
+                rings
+                    .iter()
+                    .map(|ring| {
+                        ring.iter()
+                            .map(|&(longitude, latitude)| {
+                                grid.lon_lat_to_grid_position(longitude, latitude)
+                                    .map_err(|e| DatasetError::InvalidArea(e.to_string()))
+                            })
+                            .collect::<Result<Vec<(f64, f64)>, DatasetError>>()
+                    })
+                    .collect::<Result<Vec<Vec<(f64, f64)>>, DatasetError>>()
+            })
+            .collect::<Result<Vec<Vec<Vec<(f64, f64)>>>, DatasetError>>()?;
+
+        let exterior_points: Vec<(f64, f64)> = polygons
+            .iter()
+            .filter_map(|rings| rings.first())
+            .flatten()
+            .copied()
+            .collect();
+        if exterior_points.is_empty() {
+            return Err(DatasetError::InvalidArea("Area has no points".to_string()));
+        }
+
+        let max_col = grid.number_of_columns as f64 - 1.0;
+        let max_row = grid.number_of_rows as f64 - 1.0;
+        let (min_x, min_y, max_x, max_y) = exterior_points.iter().fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(min_x, min_y, max_x, max_y), &(x, y)| {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            },
+        );
+        if max_x < -0.5 || max_y < -0.5 || min_x > max_col + 0.5 || min_y > max_row + 0.5 {
+            return Err(DatasetError::OutOfBounds(
+                "Area is outside the grid".to_string(),
+            ));
+        }
+
+        let mut positions = Vec::new();
+        for row in min_y.max(0.0).ceil() as usize..=max_y.min(max_row).floor() as usize {
+            for col in min_x.max(0.0).ceil() as usize..=max_x.min(max_col).floor() as usize {
+                let point = (col as f64, row as f64);
+                if polygons.iter().any(|rings| contains(rings, point)) {
+                    positions.push((col, row));
+                }
+            }
+        }
+
+        if positions.is_empty() {
+            // the area does not contain a pixel center, use the pixel at the center
+            let count = exterior_points.len() as f64;
+            let col = (exterior_points.iter().map(|p| p.0).sum::<f64>() / count).round();
+            let row = (exterior_points.iter().map(|p| p.1).sum::<f64>() / count).round();
+            if col < 0.0 || row < 0.0 || col > max_col || row > max_row {
+                return Err(DatasetError::OutOfBounds(
+                    "Area is outside the grid".to_string(),
+                ));
+            }
+
+            positions.push((col as usize, row as usize));
+        }
+
+        Ok(positions)
+    }
+
+    /// Returns the area as polygons in longitude and latitude, the edges of a bounding box
+    /// are densified.
+    fn polygons(&self) -> Vec<Vec<Vec<(f64, f64)>>> {
+        match self {
+            Area::BoundingBox {
+                min_lon,
+                min_lat,
+                max_lon,
+                max_lat,
+            } => {
+                let corners = [
+                    (*min_lon, *min_lat),
+                    (*max_lon, *min_lat),
+                    (*max_lon, *max_lat),
+                    (*min_lon, *max_lat),
+                ];
+                let ring = (0..corners.len())
+                    .flat_map(|i| {
+                        let (x0, y0) = corners[i];
+                        let (x1, y1) = corners[(i + 1) % corners.len()];
+                        (0..BBOX_EDGE_POINTS).map(move |j| {
+                            let t = j as f64 / BBOX_EDGE_POINTS as f64;
+                            (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t)
+                        })
+                    })
+                    .collect();
+                vec![vec![ring]]
+            }
+            Area::Polygons(polygons) => polygons.clone(),
+        }
+    }
+}
+
+/// Even-odd test if the point is inside the polygon, points inside a hole are outside.
+fn contains(rings: &[Vec<(f64, f64)>], (x, y): (f64, f64)) -> bool {
+    let mut inside = false;
+    for ring in rings {
+        for i in 0..ring.len() {
+            let (x0, y0) = ring[i];
+            let (x1, y1) = ring[(i + 1) % ring.len()];
+            if (y0 > y) != (y1 > y) && x < x0 + (y - y0) / (y1 - y0) * (x1 - x0) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn collect_polygons(
+    value: &Value,
+    polygons: &mut Vec<Vec<Vec<(f64, f64)>>>,
+) -> Result<(), DatasetError> {
+    let geojson_type = value
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    match geojson_type {
+        "FeatureCollection" => {
+            let features = value
+                .get("features")
+                .and_then(Value::as_array)
+                .ok_or_else(|| invalid_geojson("FeatureCollection without features"))?;
+            for feature in features {
+                collect_polygons(feature, polygons)?;
+            }
+        }
+        "Feature" => {
+            let geometry = value
+                .get("geometry")
+                .ok_or_else(|| invalid_geojson("Feature without geometry"))?;
+            if !geometry.is_null() {
+                collect_polygons(geometry, polygons)?;
+            }
+        }
+        "GeometryCollection" => {
+            let geometries = value
+                .get("geometries")
+                .and_then(Value::as_array)
+                .ok_or_else(|| invalid_geojson("GeometryCollection without geometries"))?;
+            for geometry in geometries {
+                collect_polygons(geometry, polygons)?;
+            }
+        }
+        "Polygon" => polygons.push(parse_polygon(coordinates(value)?)?),
+        "MultiPolygon" => {
+            let coordinates = coordinates(value)?
+                .as_array()
+                .ok_or_else(|| invalid_geojson("MultiPolygon coordinates should be an array"))?;
+            for polygon in coordinates {
+                polygons.push(parse_polygon(polygon)?);
+            }
+        }
+        other => {
+            return Err(invalid_geojson(&format!(
+                "Unsupported GeoJSON type: {}",
+                other
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+fn coordinates(value: &Value) -> Result<&Value, DatasetError> {
+    value
+        .get("coordinates")
+        .ok_or_else(|| invalid_geojson("Geometry without coordinates"))
+}
+
+fn parse_polygon(value: &Value) -> Result<Vec<Vec<(f64, f64)>>, DatasetError> {
+    let rings = value
+        .as_array()
+        .ok_or_else(|| invalid_geojson("Polygon coordinates should be an array of rings"))?;
+
+    rings
+        .iter()
+        .map(|ring| {
+            let points = ring
+                .as_array()
+                .ok_or_else(|| invalid_geojson("Polygon ring should be an array of positions"))?
+                .iter()
+                .map(|position| match position.as_array().map(Vec::as_slice) {
+                    Some([lon, lat, ..]) => match (lon.as_f64(), lat.as_f64()) {
+                        (Some(lon), Some(lat)) => Ok((lon, lat)),
+                        _ => Err(invalid_geojson("Position should contain numbers")),
+                    },
+                    _ => Err(invalid_geojson(
+                        "Position should contain at least 2 numbers",
+                    )),
+                })
+                .collect::<Result<Vec<(f64, f64)>, DatasetError>>()?;
+
+            if points.len() < 3 {
+                return Err(invalid_geojson(
+                    "Polygon ring should have at least 3 positions",
+                ));
+            }
+            Ok(points)
+        })
+        .collect()
+}
+
+fn invalid_geojson(message: &str) -> DatasetError {
+    DatasetError::InvalidArea(format!("Invalid GeoJSON: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_geojson() {
+        let area = Area::from_geojson(
+            r#"{
+                "type": "FeatureCollection",
+                "features": [
+                    {"type": "Feature", "properties": {}, "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [[[5.0, 52.0], [5.1, 52.0], [5.1, 52.1], [5.0, 52.0]]]
+                    }},
+                    {"type": "Feature", "properties": {}, "geometry": {
+                        "type": "MultiPolygon",
+                        "coordinates": [
+                            [[[4.0, 51.0], [4.1, 51.0], [4.1, 51.1], [4.0, 51.0]]],
+                            [[[6.0, 53.0], [6.1, 53.0], [6.1, 53.1], [6.0, 53.0]]]
+                        ]
+                    }}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        match area {
+            Area::Polygons(polygons) => {
+                assert_eq!(polygons.len(), 3);
+                assert_eq!(polygons[2][0][1], (6.1, 53.0));
+            }
+            _ => panic!("expected polygons"),
+        }
+
+        for invalid in [
+            "not json",
+            r#"{"type": "Point", "coordinates": [5.0, 52.0]}"#,
+            r#"{"type": "Polygon", "coordinates": [[[5.0, 52.0], [5.1, 52.0]]]}"#,
+            r#"{"type": "FeatureCollection", "features": []}"#,
+        ] {
+            assert!(matches!(
+                Area::from_geojson(invalid).unwrap_err(),
+                DatasetError::InvalidArea(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_grid_positions() {
+        let grid = GridDefinition::nl25();
+        let bbox = Area::bbox(5.0, 52.0, 5.2, 52.1).unwrap();
+        let positions = bbox.grid_positions(grid).unwrap();
+        // a 0.2 by 0.1 degree box is roughly 14 by 11 km
+        assert!(positions.len() > 100 && positions.len() < 200);
+
+        let (col, row) = grid.lon_lat_to_grid(5.1, 52.05).unwrap();
+        assert!(positions.contains(&(col as usize, row as usize)));
+        let (col, row) = grid.lon_lat_to_grid(5.3, 52.05).unwrap();
+        assert!(!positions.contains(&(col as usize, row as usize)));
+
+        // the same box as polygon with a hole in the middle
+        let polygon = Area::Polygons(vec![vec![
+            vec![(5.0, 52.0), (5.2, 52.0), (5.2, 52.1), (5.0, 52.1)],
+            vec![(5.08, 52.04), (5.12, 52.04), (5.12, 52.06), (5.08, 52.06)],
+        ]]);
+        let polygon_positions = polygon.grid_positions(grid).unwrap();
+        assert!(polygon_positions.len() < positions.len());
+        let (col, row) = grid.lon_lat_to_grid(5.1, 52.05).unwrap();
+        assert!(!polygon_positions.contains(&(col as usize, row as usize)));
+
+        // an area smaller than a pixel returns the pixel at the center
+        let small = Area::bbox(5.1, 52.05, 5.1001, 52.0501).unwrap();
+        assert_eq!(small.grid_positions(grid).unwrap().len(), 1);
+
+        assert!(matches!(
+            Area::bbox(-30.0, 30.0, -29.0, 31.0)
+                .unwrap()
+                .grid_positions(grid)
+                .unwrap_err(),
+            DatasetError::OutOfBounds(_)
+        ));
+        assert!(matches!(
+            Area::bbox(5.2, 52.0, 5.0, 52.1).unwrap_err(),
+            DatasetError::InvalidArea(_)
+        ));
+    }
+}
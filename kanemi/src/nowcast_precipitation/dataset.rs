@@ -1,12 +1,20 @@
+use super::area::Area;
+use super::export::{save_geotiff, ExportValues};
+use super::grid::{read_f64_attr_value, read_string_attr, GridDefinition};
 use super::image::Image;
-use super::transformation::{convert_hdf5_datetime, pixel_to_mm_hr};
+use super::render::{save_animation, RenderOptions};
+use super::transformation::{convert_hdf5_datetime, parse_calibration_formula, Calibration};
 use crate::errors::DatasetError;
-use crate::nowcast_precipitation::models::{PrecipitationForecast, PrecipitationForecastValue};
+use crate::nowcast_precipitation::models::{
+    AreaForecast, AreaForecastValue, PrecipitationForecast, PrecipitationForecastValue,
+};
 use hdf5::types::FixedAscii;
 use hdf5::File;
 use hdf5::Group;
 use hdf5::Result;
 use ndarray::{ArrayBase, Ix2, OwnedRepr};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 /// Represents a dataset containing multiple images.
 #[derive(Debug)]
@@ -14,15 +22,22 @@ pub struct Dataset {
     pub filepath: String,
     pub hdf5_file: File,
     pub image_count: u32,
+    /// grid geometry and projection read from the file
+    pub grid: GridDefinition,
+    /// images kept in memory after the first read, None when caching is disabled
+    image_cache: Option<OnceLock<Vec<Image>>>,
 }
 
 impl Dataset {
     /// Constructs a new Dataset from the given file path.
     /// The file path should point to a valid HDF5 file.
     ///
+    /// The grid definition and number of images are read from the file.
+    ///
     /// # Errors
     /// - `DatasetError::FileNotFound`: If the file does not exist.
     /// - `DatasetError::ReadError`: If an error occurs while reading the file.
+    /// - `DatasetError::FormatError`: If the grid definition or number of images can not be read.
     pub fn new(filepath: String) -> Result<Dataset, DatasetError> {
         if filepath.is_empty() || !std::path::Path::new(&filepath).exists() {
             return Err(DatasetError::FileNotFound(format!(
@@ -36,24 +51,56 @@ impl Dataset {
             return Err(DatasetError::ReadError(e.to_string()));
         }
 
+        let hdf5_file = hdf5_file.unwrap();
+        let grid = GridDefinition::from_hdf5(&hdf5_file)?;
+        let image_count = read_image_count(&hdf5_file)?;
+
         Ok(Dataset {
             filepath,
-            hdf5_file: hdf5_file.unwrap(),
-            image_count: 25,
+            hdf5_file,
+            image_count,
+            grid,
+            image_cache: None,
         })
     }
 
+    /// Keep the images in memory after they are read for the first time, subsequent
+    /// forecasts are calculated without reading the HDF5 file. The images of a dataset
+    /// take about 25 MB of memory.
+    pub fn with_image_cache(mut self) -> Self {
+        self.image_cache = Some(OnceLock::new());
+        self
+    }
+
+    /// Remove the cached images from memory, the images are read again on the next forecast.
+    pub fn clear_image_cache(&mut self) {
+        if let Some(image_cache) = self.image_cache.as_mut() {
+            image_cache.take();
+        }
+    }
+
+    /// Reads all images of the dataset in order.
+    ///
+    /// # Errors
+    /// - `DatasetError::ReadError`: If an error occurs while reading an image.
+    pub fn read_images(&self) -> Result<Vec<Image>, DatasetError> {
+        (1..=self.image_count)
+            .map(|image_index| self.read_image(image_index))
+            .collect()
+    }
+
     /// Reads the image with the given index from the dataset.
-    /// The index should be in the range [1, 25].
-    /// The image contains the pixel data and the datetime of the image.
+    /// The index should be in the range [1, image_count].
+    /// The image contains the pixel data, the datetime and the calibration of the image.
     ///
     /// # Errors
     /// - `DatasetError::ReadError`: If an error occurs while reading the image.
+    /// - `DatasetError::FormatError`: If the datetime or calibration of the image can not be parsed.
     pub fn read_image(&self, image_index: u32) -> Result<Image, DatasetError> {
         if image_index < 1 || image_index > self.image_count {
             return Err(DatasetError::ImageIndexOutOfBounds(format!(
-                "Image index out of bounds, should be between 1 and 25: {}",
-                image_index
+                "{}, should be between 1 and {}",
+                image_index, self.image_count
             )));
         }
 
@@ -70,18 +117,41 @@ impl Dataset {
         let datetime = self.get_image_datetime(&group_img)?;
         let img_data = self.get_image_data(&group_img)?;
 
-        let datetime = convert_hdf5_datetime(datetime.as_str().to_string()).unwrap();
+        let shape = img_data.shape();
+        if shape[0] != self.grid.number_of_rows as usize
+            || shape[1] != self.grid.number_of_columns as usize
+        {
+            return Err(DatasetError::FormatError(format!(
+                "Image{} has {}x{} pixels, expected {}x{} from the grid definition",
+                image_index,
+                shape[1],
+                shape[0],
+                self.grid.number_of_columns,
+                self.grid.number_of_rows
+            )));
+        }
+
+        let datetime = convert_hdf5_datetime(datetime.clone()).ok_or_else(|| {
+            DatasetError::FormatError(format!(
+                "Image{} has an invalid datetime: {}",
+                image_index, datetime
+            ))
+        })?;
+        let calibration = read_calibration(&group_img)?;
+
         // create the Image struct
-        let image = Image::new(img_data, datetime);
+        let image = Image::new(img_data, datetime).with_calibration(calibration);
         Ok(image)
     }
 
     /// Gets the precipitation forecast (2 hours) from the dataset for the given longitude and latitude.
-    /// The forecast contains the datetime and the precipitation value in mm/h for the next 25 images.
+    /// The forecast contains the datetime and the precipitation value in mm/h for the next 25 images,
+    /// the value is None when the pixel has no data.
     /// The longitude and latitude should be in the range of the dataset.
     ///
     /// # Errors
     /// - `DatasetError::ReadError`: If an error occurs while reading the image.
+    /// - `DatasetError::OutOfBounds`: If the location is outside the grid of the dataset.
     ///
     /// # Example
     /// ```
@@ -94,35 +164,179 @@ impl Dataset {
         longitude: f64,
         latitude: f64,
     ) -> Result<PrecipitationForecast, DatasetError> {
-        let mut forecast = PrecipitationForecast {
-            datetime: "".to_string(),
-            values: Vec::new(),
-        };
-
-        for i in 1..26 {
-            let image = self.read_image(i);
-            if image.is_err() {
-                return Err(DatasetError::ReadError("Error reading image".to_string()));
-            }
+        let mut forecasts = self.get_forecasts(&[(longitude, latitude)])?;
+        Ok(forecasts.remove(0))
+    }
 
-            let image = image.unwrap();
+    /// Gets the precipitation forecast (2 hours) for multiple locations, the images are read
+    /// once for all locations. The forecasts are returned in the order of the locations.
+    ///
+    /// # Errors
+    /// - `DatasetError::ReadError`: If an error occurs while reading the images.
+    /// - `DatasetError::OutOfBounds`: If a location is outside the grid of the dataset.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kanemi::nowcast_precipitation::dataset::Dataset;
+    /// let dataset = Dataset::new("../example_data/RAD_NL25_RAC_FM_202412222055.h5".to_string())
+    ///     .unwrap()
+    ///     .with_image_cache();
+    /// let forecasts = dataset.get_forecasts(&[(5.0, 52.0), (4.9, 52.37)]).unwrap();
+    /// ```
+    pub fn get_forecasts(
+        &self,
+        locations: &[(f64, f64)],
+    ) -> Result<Vec<PrecipitationForecast>, DatasetError> {
+        self.with_images(|images| forecasts_from_images(&self.grid, images, locations))
+    }
 
-            // ToDo: Get the real file datetime
-            if i == 1 {
-                forecast.datetime = image.datetime.format("%Y-%m-%dT%H:%M:%SZ").to_string();
-            }
+    /// Gets the precipitation forecast (2 hours) aggregated over an area, for every image
+    /// the mean and max precipitation in mm/h and the fraction of the area with precipitation.
+    /// Pixels without data are left out of the statistics.
+    /// The area is rasterised onto the grid, a pixel is part of the area when its center is
+    /// inside the area.
+    ///
+    /// # Errors
+    /// - `DatasetError::ReadError`: If an error occurs while reading the images.
+    /// - `DatasetError::OutOfBounds`: If the area is outside the grid of the dataset.
+    /// - `DatasetError::InvalidArea`: If the area can not be projected to the grid.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kanemi::nowcast_precipitation::area::Area;
+    /// use kanemi::nowcast_precipitation::dataset::Dataset;
+    /// let dataset = Dataset::new("../example_data/RAD_NL25_RAC_FM_202412222055.h5".to_string()).unwrap();
+    /// let area = Area::bbox(4.8, 52.3, 5.0, 52.4).unwrap();
+    /// let forecast = dataset.get_area_forecast(&area).unwrap();
+    /// ```
+    pub fn get_area_forecast(&self, area: &Area) -> Result<AreaForecast, DatasetError> {
+        let positions = area.grid_positions(&self.grid)?;
+        self.with_images(|images| area_forecast_from_images(images, &positions))
+    }
 
-            let value = image.get_value_at_lon_lat(longitude, latitude).unwrap();
-            let mm_per_hour = pixel_to_mm_hr(value.unwrap());
-            let iso_datetime = image.datetime.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    /// Renders every image of the dataset to a PNG in the output directory, the files are
+    /// named after the dataset file and the image index. Returns the paths of the images.
+    ///
+    /// # Errors
+    /// - `DatasetError::ReadError`: If an error occurs while reading the images.
+    /// - `DatasetError::WriteError`: If an image can not be written.
+    pub fn render_frames(
+        &self,
+        output_dir: impl AsRef<Path>,
+        options: &RenderOptions,
+    ) -> Result<Vec<PathBuf>, DatasetError> {
+        let output_dir = output_dir.as_ref();
+        let name = self.file_stem();
+
+        self.with_images(|images| {
+            images
+                .iter()
+                .enumerate()
+                .map(|(i, image)| {
+                    let path = output_dir.join(format!("{}_{:02}.png", name, i + 1));
+                    image.save_png(&path, options)?;
+                    Ok(path)
+                })
+                .collect()
+        })
+    }
 
-            forecast.values.push(PrecipitationForecastValue {
-                datetime: iso_datetime,
-                value: mm_per_hour,
-            });
-        }
+    /// Renders the images of the dataset as animated GIF with a frame for every image.
+    ///
+    /// # Errors
+    /// - `DatasetError::ReadError`: If an error occurs while reading the images.
+    /// - `DatasetError::WriteError`: If the animation can not be written.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kanemi::nowcast_precipitation::dataset::Dataset;
+    /// use kanemi::nowcast_precipitation::render::RenderOptions;
+    /// let dataset = Dataset::new("../example_data/RAD_NL25_RAC_FM_202412222055.h5".to_string()).unwrap();
+    /// dataset.render_animation("./radar.gif", &RenderOptions::default()).unwrap();
+    /// ```
+    pub fn render_animation(
+        &self,
+        path: impl AsRef<Path>,
+        options: &RenderOptions,
+    ) -> Result<(), DatasetError> {
+        self.with_images(|images| save_animation(path, images, options))
+    }
 
-        Ok(forecast)
+    /// Exports all images of the dataset as GeoTIFF with a band for every image.
+    ///
+    /// # Errors
+    /// - `DatasetError::ReadError`: If an error occurs while reading the images.
+    /// - `DatasetError::FormatError`: If the grid of the dataset can not be exported.
+    /// - `DatasetError::WriteError`: If the GeoTIFF can not be written.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kanemi::nowcast_precipitation::dataset::Dataset;
+    /// use kanemi::nowcast_precipitation::export::ExportValues;
+    /// let dataset = Dataset::new("../example_data/RAD_NL25_RAC_FM_202412222055.h5".to_string()).unwrap();
+    /// dataset.export_geotiff("./radar.tif", ExportValues::MmPerHour).unwrap();
+    /// ```
+    pub fn export_geotiff(
+        &self,
+        path: impl AsRef<Path>,
+        values: ExportValues,
+    ) -> Result<(), DatasetError> {
+        self.with_images(|images| save_geotiff(path, &self.grid, images, values))
+    }
+
+    /// Exports every image of the dataset as Esri ASCII grid with a .prj file to the output
+    /// directory, the files are named after the dataset file and the image index. Returns
+    /// the paths of the grids.
+    ///
+    /// # Errors
+    /// - `DatasetError::ReadError`: If an error occurs while reading the images.
+    /// - `DatasetError::FormatError`: If the grid of the dataset can not be exported.
+    /// - `DatasetError::WriteError`: If a grid can not be written.
+    pub fn export_ascii_grids(
+        &self,
+        output_dir: impl AsRef<Path>,
+        values: ExportValues,
+    ) -> Result<Vec<PathBuf>, DatasetError> {
+        let output_dir = output_dir.as_ref();
+        let name = self.file_stem();
+
+        self.with_images(|images| {
+            images
+                .iter()
+                .enumerate()
+                .map(|(i, image)| {
+                    let path = output_dir.join(format!("{}_{:02}.asc", name, i + 1));
+                    image.save_ascii_grid(&path, &self.grid, values)?;
+                    Ok(path)
+                })
+                .collect()
+        })
+    }
+
+    /// Name of the dataset file without extension, used to name exported files.
+    fn file_stem(&self) -> String {
+        Path::new(&self.filepath)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "nowcast".to_string())
+    }
+
+    /// Call the function with the images of the dataset, the cached images are used when
+    /// the image cache is enabled.
+    fn with_images<T>(
+        &self,
+        f: impl FnOnce(&[Image]) -> Result<T, DatasetError>,
+    ) -> Result<T, DatasetError> {
+        match &self.image_cache {
+            Some(image_cache) => {
+                if image_cache.get().is_none() {
+                    let _ = image_cache.set(self.read_images()?);
+                }
+
+                f(image_cache.get().unwrap())
+            }
+            None => f(&self.read_images()?),
+        }
     }
 
     fn get_image_datetime(&self, group: &Group) -> Result<String, DatasetError> {
@@ -167,12 +381,251 @@ impl Dataset {
     }
 }
 
+/// Reads the number of images from the `overview` group.
+fn read_image_count(file: &File) -> Result<u32, DatasetError> {
+    file.group("overview")
+        .and_then(|overview| overview.attr("number_image_groups"))
+        .and_then(|attr| attr.read_raw::<u32>())
+        .ok()
+        .and_then(|values| values.first().copied())
+        .ok_or_else(|| {
+            DatasetError::FormatError("Error reading number_image_groups attribute".to_string())
+        })
+}
+
+/// Reads the calibration of an image from its `calibration` group, the default calibration
+/// is used when the group is missing.
+fn read_calibration(group: &Group) -> Result<Calibration, DatasetError> {
+    let group = match group.group("calibration") {
+        Ok(group) => group,
+        Err(_) => return Ok(Calibration::default()),
+    };
+
+    let formula = read_string_attr(&group, "calibration_formulas")?;
+    let (gain, offset) = parse_calibration_formula(&formula).ok_or_else(|| {
+        DatasetError::FormatError(format!("Invalid calibration formula: {}", formula))
+    })?;
+
+    let default = Calibration::default();
+    let nodata_value = |name: &str, default: Option<u16>| match group.attr(name) {
+        Ok(_) => read_f64_attr_value(&group, name).map(|value| Some(value as u16)),
+        Err(_) => Ok(default),
+    };
+
+    Ok(Calibration {
+        gain,
+        offset,
+        missing_data: nodata_value("calibration_missing_data", default.missing_data)?,
+        out_of_image: nodata_value("calibration_out_of_image", default.out_of_image)?,
+    })
+}
+
+/// Create the forecast of every location from the images.
+fn forecasts_from_images(
+    grid: &GridDefinition,
+    images: &[Image],
+    locations: &[(f64, f64)],
+) -> Result<Vec<PrecipitationForecast>, DatasetError> {
+    // project every location once instead of once per image
+    let positions = locations
+        .iter()
+        .map(|&(longitude, latitude)| {
+            grid.lon_lat_to_grid(longitude, latitude)
+                .map(|(x, y)| (x as usize, y as usize))
+                .map_err(|e| DatasetError::OutOfBounds(e.to_string()))
+        })
+        .collect::<Result<Vec<(usize, usize)>, DatasetError>>()?;
+
+    let datetimes: Vec<String> = images
+        .iter()
+        .map(|image| image.datetime.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .collect();
+
+    positions
+        .iter()
+        .zip(locations)
+        .map(|(&(x, y), &(longitude, latitude))| {
+            let values = images
+                .iter()
+                .zip(&datetimes)
+                .map(|(image, datetime)| {
+                    let value = image.get_value_at_position(x, y).ok_or_else(|| {
+                        DatasetError::OutOfBounds(format!(
+                            "Location {}, {} is outside the image",
+                            longitude, latitude
+                        ))
+                    })?;
+
+                    Ok(PrecipitationForecastValue {
+                        datetime: datetime.clone(),
+                        value: image.calibration.to_mm_hr(value),
+                    })
+                })
+                .collect::<Result<Vec<PrecipitationForecastValue>, DatasetError>>()?;
+
+            // ToDo: Get the real file datetime
+            Ok(PrecipitationForecast {
+                datetime: datetimes.first().cloned().unwrap_or_default(),
+                values,
+            })
+        })
+        .collect()
+}
+
+/// Create the area forecast from the images for the pixels at the (column, row) positions.
+fn area_forecast_from_images(
+    images: &[Image],
+    positions: &[(usize, usize)],
+) -> Result<AreaForecast, DatasetError> {
+    let values = images
+        .iter()
+        .map(|image| {
+            let mut sum = 0.0;
+            let mut max: Option<f64> = None;
+            let mut wet = 0;
+            let mut missing = 0;
+            for &(x, y) in positions {
+                let value = image.get_value_at_position(x, y).ok_or_else(|| {
+                    DatasetError::OutOfBounds(format!(
+                        "Grid position {}, {} is outside the image",
+                        x, y
+                    ))
+                })?;
+
+                let Some(value) = image.calibration.to_mm_hr(value) else {
+                    missing += 1;
+                    continue;
+                };
+
+                sum += value;
+                max = Some(max.map_or(value, |max| max.max(value)));
+                if value > 0.0 {
+                    wet += 1;
+                }
+            }
+
+            let count = (positions.len() - missing) as f64;
+            let valid = count > 0.0;
+            Ok(AreaForecastValue {
+                datetime: image.datetime.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                missing_count: missing,
+                mean: valid.then(|| (sum / count * 100.0).round() / 100.0),
+                max,
+                coverage: valid.then(|| wet as f64 / count),
+            })
+        })
+        .collect::<Result<Vec<AreaForecastValue>, DatasetError>>()?;
+
+    Ok(AreaForecast {
+        datetime: values
+            .first()
+            .map(|value| value.datetime.clone())
+            .unwrap_or_default(),
+        pixel_count: positions.len(),
+        values,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::nowcast_precipitation::projection;
     use chrono::*;
 
+    #[test]
+    fn test_forecasts_from_images() {
+        let images: Vec<Image> = (0..3)
+            .map(|i| {
+                let data = ArrayBase::from_shape_fn((765, 700), |(_, x)| (x + i) as u16);
+                let datetime = NaiveDate::from_ymd_opt(2024, 12, 22)
+                    .unwrap()
+                    .and_hms_opt(20, 55 + i as u32, 0)
+                    .unwrap();
+                Image::new(data, datetime)
+            })
+            .collect();
+
+        let locations = [(0.0, 55.9736), (5.35192613, 51.71680934)];
+        let grid = GridDefinition::nl25();
+        let forecasts = forecasts_from_images(grid, &images, &locations).unwrap();
+        assert_eq!(forecasts.len(), 2);
+        assert_eq!(forecasts[0].datetime, "2024-12-22T20:55:00Z");
+        assert_eq!(forecasts[0].values.len(), 3);
+        assert_eq!(forecasts[0].values[2].datetime, "2024-12-22T20:57:00Z");
+
+        for (forecast, &(lon, lat)) in forecasts.iter().zip(&locations) {
+            for (value, image) in forecast.values.iter().zip(&images) {
+                assert_eq!(
+                    value.value,
+                    image.get_mmhhr_at_lon_lat(grid, lon, lat).unwrap()
+                );
+            }
+        }
+
+        assert!(matches!(
+            forecasts_from_images(grid, &images, &[(-20.0, 40.0)]).unwrap_err(),
+            DatasetError::OutOfBounds(_)
+        ));
+
+        // the top right corner of the grid is outside the image
+        assert!(matches!(
+            forecasts_from_images(grid, &images, &[projection::GEO_TOP_RIGHT]).unwrap_err(),
+            DatasetError::OutOfBounds(_)
+        ));
+
+        // pixels without data have no value
+        let mut images = images;
+        images[1].data[[0, 0]] = 65535;
+        let forecasts = forecasts_from_images(grid, &images, &[(0.0, 55.9736)]).unwrap();
+        assert_eq!(forecasts[0].values[0].value, Some(0.0));
+        assert_eq!(forecasts[0].values[1].value, None);
+    }
+
+    #[test]
+    fn test_area_forecast_from_images() {
+        let images: Vec<Image> = (0..2)
+            .map(|i| {
+                let data = ArrayBase::from_shape_fn((765, 700), |(_, x)| (x * i) as u16);
+                let datetime = NaiveDate::from_ymd_opt(2024, 12, 22)
+                    .unwrap()
+                    .and_hms_opt(20, 55 + i as u32, 0)
+                    .unwrap();
+                Image::new(data, datetime)
+            })
+            .collect();
+
+        let positions = [(0, 10), (1, 10), (2, 10), (3, 11)];
+        let forecast = area_forecast_from_images(&images, &positions).unwrap();
+        assert_eq!(forecast.datetime, "2024-12-22T20:55:00Z");
+        assert_eq!(forecast.pixel_count, 4);
+        assert_eq!(forecast.values.len(), 2);
+        assert_eq!(forecast.values[0].mean, Some(0.0));
+        assert_eq!(forecast.values[0].coverage, Some(0.0));
+        assert_eq!(forecast.values[1].datetime, "2024-12-22T20:56:00Z");
+        assert_eq!(forecast.values[1].missing_count, 0);
+        assert_eq!(forecast.values[1].mean, Some(0.18));
+        assert_eq!(forecast.values[1].max, Some(0.36));
+        assert_eq!(forecast.values[1].coverage, Some(0.75));
+
+        // pixels without data are left out of the statistics
+        let mut images = images;
+        images[1].data[[11, 3]] = 65535;
+        images[0].data.fill(65535);
+        let forecast = area_forecast_from_images(&images, &positions).unwrap();
+        assert_eq!(forecast.values[0].missing_count, 4);
+        assert_eq!(forecast.values[0].mean, None);
+        assert_eq!(forecast.values[0].coverage, None);
+        assert_eq!(forecast.values[1].missing_count, 1);
+        assert_eq!(forecast.values[1].mean, Some(0.12));
+        assert_eq!(forecast.values[1].max, Some(0.24));
+        assert_eq!(forecast.values[1].coverage, Some(2.0 / 3.0));
+
+        assert!(matches!(
+            area_forecast_from_images(&images, &[(700, 0)]).unwrap_err(),
+            DatasetError::OutOfBounds(_)
+        ));
+    }
+
     #[test]
     fn test_file_not_found() {
         assert!(matches!(
@@ -201,6 +654,24 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_grid_definition() {
+        let dataset =
+            Dataset::new("../example_data/RAD_NL25_RAC_FM_202412222055.h5".to_string()).unwrap();
+        let grid = &dataset.grid;
+        let nl25 = GridDefinition::nl25();
+
+        assert_eq!(dataset.image_count, 25);
+        assert_eq!(grid.number_of_columns, nl25.number_of_columns);
+        assert_eq!(grid.number_of_rows, nl25.number_of_rows);
+        assert!((grid.row_offset - nl25.row_offset).abs() < 0.001);
+        assert!((grid.pixel_size_x.abs() - nl25.pixel_size_x).abs() < 0.001);
+        assert_eq!(
+            grid.lon_lat_to_grid(5.35192613, 51.71680934).unwrap(),
+            nl25.lon_lat_to_grid(5.35192613, 51.71680934).unwrap()
+        );
+    }
+
     #[test]
     fn test_read_image_1() {
         let dataset =
@@ -228,7 +699,10 @@ mod tests {
         );
 
         // Check if the same value is returned when using lon and lat
-        assert_eq!(image.get_value_at_lon_lat(lon, lat).unwrap(), Some(46));
+        assert_eq!(
+            image.get_value_at_lon_lat(&dataset.grid, lon, lat).unwrap(),
+            Some(46)
+        );
     }
 
     #[test]
@@ -244,7 +718,7 @@ mod tests {
 
         // Check if the values are correct
         assert_eq!(forecast.values.len(), 25);
-        assert_eq!(forecast.values[0].value, 0.0);
-        assert_eq!(forecast.values[15].value, 4.68);
+        assert_eq!(forecast.values[0].value, Some(0.0));
+        assert_eq!(forecast.values[15].value, Some(4.68));
     }
 }
@@ -0,0 +1,619 @@
+//! Export nowcast images to georeferenced rasters, GeoTIFF and Esri ASCII grid.
+//!
+//! The rasters use the polar stereographic projection of the grid, coordinates are written
+//! in metres. Every pixel covers the area around the grid position of its center, the same
+//! convention as `GridDefinition::lon_lat_to_grid`.
+
+use super::grid::GridDefinition;
+use super::image::Image;
+use super::transformation::Calibration;
+use crate::errors::DatasetError;
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::Path;
+use tiff::encoder::compression::{CompressionAlgorithm, Deflate};
+use tiff::encoder::TiffEncoder;
+use tiff::tags::{
+    CompressionMethod, PhotometricInterpretation, PlanarConfiguration, SampleFormat, Tag,
+};
+
+/// Value written for pixels without data when exporting precipitation in mm/h.
+const NODATA: f64 = -9999.0;
+
+/// Values written to the exported rasters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportValues {
+    /// raw pixel values as stored in the HDF5 file, pixels outside the radar range are
+    /// written as the missing data value
+    Raw,
+    /// precipitation in mm/h, pixels without data are -9999
+    MmPerHour,
+}
+
+impl ExportValues {
+    /// Value written for the pixel, pixels without data or outside the radar range are
+    /// written as the nodata value so a raster only has a single nodata value.
+    fn value(&self, calibration: &Calibration, pixel: u16, nodata: Option<f64>) -> f64 {
+        match (self, nodata) {
+            (_, Some(nodata)) if calibration.is_missing(pixel) => nodata,
+            (ExportValues::Raw, _) => pixel as f64,
+            (ExportValues::MmPerHour, _) => calibration.to_mm_hr(pixel).unwrap_or(NODATA),
+        }
+    }
+
+    /// Value of the pixels without data, the missing data value of the calibration (or the
+    /// value of pixels outside the radar range when it has none) for raw pixel values.
+    fn nodata(&self, calibration: &Calibration) -> Option<f64> {
+        match self {
+            ExportValues::Raw => calibration
+                .missing_data
+                .or(calibration.out_of_image)
+                .map(|value| value as f64),
+            ExportValues::MmPerHour => Some(NODATA),
+        }
+    }
+}
+
+/// Polar stereographic projection parameters parsed from the proj4 string of the grid.
+#[derive(Debug, PartialEq)]
+struct PolarStereographic {
+    lat_0: f64,
+    lon_0: f64,
+    lat_ts: f64,
+    x_0: f64,
+    y_0: f64,
+    /// semi-major and semi-minor axis in metres
+    a: f64,
+    b: f64,
+    /// size of a projection unit in metres, KNMI grids are defined in km
+    to_metre: f64,
+}
+
+impl PolarStereographic {
+    fn from_proj4(proj4_params: &str) -> Result<PolarStereographic, DatasetError> {
+        let params: Vec<(&str, &str)> = proj4_params
+            .split_whitespace()
+            .filter_map(|param| param.trim_start_matches('+').split_once('='))
+            .collect();
+        let param = |name: &str| -> Option<f64> {
+            params
+                .iter()
+                .find(|(key, _)| *key == name)
+                .and_then(|(_, value)| value.parse().ok())
+        };
+
+        if !params.contains(&("proj", "stere")) {
+            return Err(DatasetError::FormatError(format!(
+                "Only polar stereographic grids can be exported: {}",
+                proj4_params
+            )));
+        }
+
+        let lat_0 = param("lat_0").unwrap_or(90.0);
+        if lat_0.abs() != 90.0 {
+            return Err(DatasetError::FormatError(format!(
+                "Only polar stereographic grids can be exported, lat_0 should be 90 or -90: {}",
+                proj4_params
+            )));
+        }
+
+        let (a, b) = match (param("a"), param("b")) {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                return Err(DatasetError::FormatError(format!(
+                    "Ellipsoid axes +a and +b are required: {}",
+                    proj4_params
+                )))
+            }
+        };
+        // the units follow the ellipsoid, an ellipsoid of a few thousand units is in km
+        let to_metre = if a < 100_000.0 { 1000.0 } else { 1.0 };
+
+        Ok(PolarStereographic {
+            lat_0,
+            lon_0: param("lon_0").unwrap_or(0.0),
+            lat_ts: param("lat_ts").unwrap_or(lat_0),
+            x_0: param("x_0").unwrap_or(0.0) * to_metre,
+            y_0: param("y_0").unwrap_or(0.0) * to_metre,
+            a: a * to_metre,
+            b: b * to_metre,
+            to_metre,
+        })
+    }
+
+    /// Inverse flattening of the ellipsoid, Esri WKT uses 0 for a sphere.
+    fn inverse_flattening(&self) -> f64 {
+        if self.a == self.b {
+            0.0
+        } else {
+            self.a / (self.a - self.b)
+        }
+    }
+
+    /// Projection as Esri WKT, used for the .prj file of an ASCII grid.
+    fn esri_wkt(&self) -> String {
+        let projection = if self.lat_0 > 0.0 {
+            "Stereographic_North_Pole"
+        } else {
+            "Stereographic_South_Pole"
+        };
+
+        format!(
+            concat!(
+                "PROJCS[\"KNMI_Radar_Polar_Stereographic\",",
+                "GEOGCS[\"GCS_KNMI_Radar\",DATUM[\"D_KNMI_Radar\",SPHEROID[\"KNMI_Radar\",{},{}]],",
+                "PRIMEM[\"Greenwich\",0.0],UNIT[\"Degree\",0.0174532925199433]],",
+                "PROJECTION[\"{}\"],",
+                "PARAMETER[\"False_Easting\",{}],PARAMETER[\"False_Northing\",{}],",
+                "PARAMETER[\"Central_Meridian\",{}],PARAMETER[\"Standard_Parallel_1\",{}],",
+                "UNIT[\"Meter\",1.0]]"
+            ),
+            self.a,
+            self.inverse_flattening(),
+            projection,
+            self.x_0,
+            self.y_0,
+            self.lon_0,
+            self.lat_ts
+        )
+    }
+}
+
+/// Raster geometry in metres, the origin is the top left corner of the top left pixel.
+struct Raster {
+    projection: PolarStereographic,
+    width: u32,
+    height: u32,
+    origin_x: f64,
+    origin_y: f64,
+    pixel_size_x: f64,
+    pixel_size_y: f64,
+}
+
+impl Raster {
+    fn new(grid: &GridDefinition, images: &[Image]) -> Result<Raster, DatasetError> {
+        let projection = PolarStereographic::from_proj4(&grid.proj4_params)?;
+        for image in images {
+            let shape = image.data.shape();
+            if shape[0] != grid.number_of_rows as usize
+                || shape[1] != grid.number_of_columns as usize
+            {
+                return Err(DatasetError::FormatError(format!(
+                    "Image has {}x{} pixels, expected {}x{} from the grid definition",
+                    shape[1], shape[0], grid.number_of_columns, grid.number_of_rows
+                )));
+            }
+        }
+
+        let pixel_size_x = grid.pixel_size_x.abs() * projection.to_metre;
+        let pixel_size_y = grid.pixel_size_y.abs() * projection.to_metre;
+        Ok(Raster {
+            width: grid.number_of_columns,
+            height: grid.number_of_rows,
+            origin_x: (grid.column_offset - 0.5) * pixel_size_x,
+            origin_y: -(grid.row_offset - 0.5) * pixel_size_y,
+            pixel_size_x,
+            pixel_size_y,
+            projection,
+        })
+    }
+
+    /// GeoKeyDirectory, double and ascii params of a user defined polar stereographic CRS.
+    fn geo_keys(&self) -> (Vec<u16>, Vec<f64>, String) {
+        let citation = "KNMI radar polar stereographic|";
+        let geog_citation = "KNMI radar ellipsoid|";
+        let ascii = format!("{}{}", citation, geog_citation);
+        let doubles = vec![
+            self.projection.a,
+            self.projection.b,
+            self.projection.lat_ts,
+            self.projection.x_0,
+            self.projection.y_0,
+            1.0,
+            self.projection.lon_0,
+        ];
+
+        let citation_len = citation.len() as u16;
+        let geog_citation_len = geog_citation.len() as u16;
+
+        // key id, tag location, count, value or index
+        let keys: [[u16; 4]; 21] = [
+            [1024, 0, 1, 1],                                // GTModelTypeGeoKey: projected
+            [1025, 0, 1, 1],                                // GTRasterTypeGeoKey: pixel is area
+            [1026, 34737, citation_len, 0],                 // GTCitationGeoKey
+            [2048, 0, 1, 32767],                            // GeographicTypeGeoKey: user defined
+            [2049, 34737, geog_citation_len, citation_len], // GeogCitationGeoKey
+            [2050, 0, 1, 32767],                            // GeogGeodeticDatumGeoKey: user defined
+            [2051, 0, 1, 8901],                             // GeogPrimeMeridianGeoKey: Greenwich
+            [2052, 0, 1, 9001],                             // GeogLinearUnitsGeoKey: metre
+            [2054, 0, 1, 9102],                             // GeogAngularUnitsGeoKey: degree
+            [2056, 0, 1, 32767],                            // GeogEllipsoidGeoKey: user defined
+            [2057, 34736, 1, 0],                            // GeogSemiMajorAxisGeoKey
+            [2058, 34736, 1, 1],                            // GeogSemiMinorAxisGeoKey
+            [3072, 0, 1, 32767],                            // ProjectedCSTypeGeoKey: user defined
+            [3074, 0, 1, 32767],                            // ProjectionGeoKey: user defined
+            [3075, 0, 1, 15],                               // ProjCoordTransGeoKey: stereographic
+            [3076, 0, 1, 9001],                             // ProjLinearUnitsGeoKey: metre
+            [3081, 34736, 1, 2],                            // ProjNatOriginLatGeoKey: lat_ts
+            [3082, 34736, 1, 3],                            // ProjFalseEastingGeoKey
+            [3083, 34736, 1, 4],                            // ProjFalseNorthingGeoKey
+            [3092, 34736, 1, 5],                            // ProjScaleAtNatOriginGeoKey
+            [3095, 34736, 1, 6],                            // ProjStraightVertPoleLongGeoKey
+        ];
+
+        let mut directory = vec![1, 1, 0, keys.len() as u16];
+        for key in keys {
+            directory.extend_from_slice(&key);
+        }
+
+        (directory, doubles, ascii)
+    }
+}
+
+/// Writes the images as GeoTIFF with a 32 bit float band for every image, the bands are
+/// described with the datetime of the image.
+///
+/// # Errors
+/// - `DatasetError::FormatError`: If there are no images, the grid can not be exported or an image
+///   does not match the grid.
+/// - `DatasetError::WriteError`: If the GeoTIFF can not be written.
+pub fn write_geotiff<W: Write + Seek>(
+    writer: W,
+    grid: &GridDefinition,
+    images: &[Image],
+    values: ExportValues,
+) -> Result<(), DatasetError> {
+    if images.is_empty() {
+        return Err(DatasetError::FormatError(
+            "No images to write to GeoTIFF".to_string(),
+        ));
+    }
+
+    let raster = Raster::new(grid, images)?;
+    // a single nodata value for all bands, the calibration of the images can differ
+    let nodata = values.nodata(&images[0].calibration);
+    let write_error = |e: &dyn std::fmt::Display| {
+        DatasetError::WriteError(format!("Failed to write GeoTIFF: {}", e))
+    };
+
+    let mut encoder = TiffEncoder::new(writer).map_err(|e| write_error(&e))?;
+    let mut directory = encoder.image_directory().map_err(|e| write_error(&e))?;
+
+    // one deflate compressed strip for every band
+    let mut offsets = Vec::with_capacity(images.len());
+    let mut byte_counts = Vec::with_capacity(images.len());
+    for image in images {
+        let bytes: Vec<u8> = image
+            .data
+            .iter()
+            .flat_map(|&pixel| {
+                (values.value(&image.calibration, pixel, nodata) as f32).to_ne_bytes()
+            })
+            .collect();
+        let mut compressed = Vec::new();
+        Deflate::default()
+            .write_to(&mut compressed, &bytes)
+            .map_err(|e| write_error(&e))?;
+
+        let offset = directory
+            .write_data(&compressed[..])
+            .map_err(|e| write_error(&e))?;
+        offsets.push(offset as u32);
+        byte_counts.push(compressed.len() as u32);
+    }
+
+    let bands = images.len();
+    let (geo_keys, geo_doubles, geo_ascii) = raster.geo_keys();
+    let metadata = images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| {
+            format!(
+                "<Item name=\"DESCRIPTION\" sample=\"{}\" role=\"description\">{}</Item>",
+                i,
+                image.datetime.format("%Y-%m-%dT%H:%M:%SZ")
+            )
+        })
+        .collect::<String>();
+
+    let result = (|| {
+        directory.write_tag(Tag::ImageWidth, raster.width)?;
+        directory.write_tag(Tag::ImageLength, raster.height)?;
+        directory.write_tag(Tag::BitsPerSample, &vec![32u16; bands][..])?;
+        directory.write_tag(Tag::Compression, CompressionMethod::Deflate)?;
+        directory.write_tag(
+            Tag::PhotometricInterpretation,
+            PhotometricInterpretation::BlackIsZero,
+        )?;
+        directory.write_tag(Tag::StripOffsets, &offsets[..])?;
+        directory.write_tag(Tag::SamplesPerPixel, bands as u16)?;
+        directory.write_tag(Tag::RowsPerStrip, raster.height)?;
+        directory.write_tag(Tag::StripByteCounts, &byte_counts[..])?;
+        directory.write_tag(Tag::PlanarConfiguration, PlanarConfiguration::Planar)?;
+        if bands > 1 {
+            directory.write_tag(Tag::ExtraSamples, &vec![0u16; bands - 1][..])?;
+        }
+        directory.write_tag(Tag::SampleFormat, &vec![SampleFormat::IEEEFP; bands][..])?;
+        directory.write_tag(
+            Tag::ModelPixelScaleTag,
+            &[raster.pixel_size_x, raster.pixel_size_y, 0.0][..],
+        )?;
+        directory.write_tag(
+            Tag::ModelTiepointTag,
+            &[0.0, 0.0, 0.0, raster.origin_x, raster.origin_y, 0.0][..],
+        )?;
+        directory.write_tag(Tag::GeoKeyDirectoryTag, &geo_keys[..])?;
+        directory.write_tag(Tag::GeoDoubleParamsTag, &geo_doubles[..])?;
+        directory.write_tag(Tag::GeoAsciiParamsTag, geo_ascii.as_str())?;
+        if let Some(nodata) = nodata {
+            directory.write_tag(Tag::GdalNodata, nodata.to_string().as_str())?;
+        }
+        // band descriptions read by GDAL
+        directory.write_tag(
+            Tag::Unknown(42112),
+            format!("<GDALMetadata>{}</GDALMetadata>", metadata).as_str(),
+        )?;
+        directory.finish()
+    })();
+
+    result.map_err(|e| write_error(&e))
+}
+
+/// Saves the images as GeoTIFF at the path, see `write_geotiff`.
+///
+/// # Errors
+/// - `DatasetError::FormatError`: If there are no images, the grid can not be exported or an image
+///   does not match the grid.
+/// - `DatasetError::WriteError`: If the GeoTIFF can not be written.
+pub fn save_geotiff(
+    path: impl AsRef<Path>,
+    grid: &GridDefinition,
+    images: &[Image],
+    values: ExportValues,
+) -> Result<(), DatasetError> {
+    let path = path.as_ref();
+    let file = File::create(path).map_err(|e| {
+        DatasetError::WriteError(format!("Failed to create {}: {}", path.display(), e))
+    })?;
+
+    write_geotiff(BufWriter::new(file), grid, images, values)
+}
+
+/// Writes the image as Esri ASCII grid.
+///
+/// # Errors
+/// - `DatasetError::FormatError`: If the grid can not be exported or the image does not match the grid.
+/// - `DatasetError::WriteError`: If the grid can not be written.
+pub fn write_ascii_grid<W: Write>(
+    mut writer: W,
+    grid: &GridDefinition,
+    image: &Image,
+    values: ExportValues,
+) -> Result<(), DatasetError> {
+    let raster = Raster::new(grid, std::slice::from_ref(image))?;
+    let write_error =
+        |e: std::io::Error| DatasetError::WriteError(format!("Failed to write ASCII grid: {}", e));
+
+    let lower_left_y = raster.origin_y - raster.height as f64 * raster.pixel_size_y;
+    let mut header = format!(
+        "ncols {}\nnrows {}\nxllcorner {}\nyllcorner {}\n",
+        raster.width, raster.height, raster.origin_x, lower_left_y
+    );
+    // cellsize requires square pixels, GDAL also supports a separate dx and dy
+    if (raster.pixel_size_x - raster.pixel_size_y).abs() < raster.pixel_size_x * 1e-3 {
+        header.push_str(&format!("cellsize {}\n", raster.pixel_size_x));
+    } else {
+        header.push_str(&format!(
+            "dx {}\ndy {}\n",
+            raster.pixel_size_x, raster.pixel_size_y
+        ));
+    }
+    let nodata = values.nodata(&image.calibration);
+    header.push_str(&format!("NODATA_value {}\n", nodata.unwrap_or(NODATA)));
+    writer.write_all(header.as_bytes()).map_err(write_error)?;
+
+    for row in image.data.rows() {
+        let line = row
+            .iter()
+            .map(|&pixel| values.value(&image.calibration, pixel, nodata).to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        writeln!(writer, "{}", line).map_err(write_error)?;
+    }
+
+    writer.flush().map_err(write_error)
+}
+
+/// Saves the image as Esri ASCII grid at the path, the projection is written to a .prj
+/// file next to it.
+///
+/// # Errors
+/// - `DatasetError::FormatError`: If the grid can not be exported or the image does not match the grid.
+/// - `DatasetError::WriteError`: If the grid can not be written.
+pub fn save_ascii_grid(
+    path: impl AsRef<Path>,
+    grid: &GridDefinition,
+    image: &Image,
+    values: ExportValues,
+) -> Result<(), DatasetError> {
+    let path = path.as_ref();
+    let wkt = PolarStereographic::from_proj4(&grid.proj4_params)?.esri_wkt();
+    let file = File::create(path).map_err(|e| {
+        DatasetError::WriteError(format!("Failed to create {}: {}", path.display(), e))
+    })?;
+    write_ascii_grid(BufWriter::new(file), grid, image, values)?;
+
+    let prj_path = path.with_extension("prj");
+    std::fs::write(&prj_path, wkt).map_err(|e| {
+        DatasetError::WriteError(format!("Failed to write {}: {}", prj_path.display(), e))
+    })
+}
+
+impl Image {
+    /// Saves the image as single band GeoTIFF, see `save_geotiff`.
+    pub fn save_geotiff(
+        &self,
+        path: impl AsRef<Path>,
+        grid: &GridDefinition,
+        values: ExportValues,
+    ) -> Result<(), DatasetError> {
+        save_geotiff(path, grid, std::slice::from_ref(self), values)
+    }
+
+    /// Saves the image as Esri ASCII grid with a .prj file, see `save_ascii_grid`.
+    pub fn save_ascii_grid(
+        &self,
+        path: impl AsRef<Path>,
+        grid: &GridDefinition,
+        values: ExportValues,
+    ) -> Result<(), DatasetError> {
+        save_ascii_grid(path, grid, self, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use ndarray::ArrayBase;
+    use tiff::decoder::{Decoder, DecodingResult};
+
+    fn image(i: u16) -> Image {
+        let data = ArrayBase::from_shape_fn((765, 700), |(y, x)| {
+            if x == 10 && y == 20 {
+                100 + i
+            } else if x == 0 && y == 0 {
+                65535
+            } else {
+                0
+            }
+        });
+        let datetime = NaiveDate::from_ymd_opt(2024, 12, 22)
+            .unwrap()
+            .and_hms_opt(20, 55 + i as u32, 0)
+            .unwrap();
+        Image::new(data, datetime)
+    }
+
+    #[test]
+    fn test_projection_params() {
+        let grid = GridDefinition::nl25();
+        let projection = PolarStereographic::from_proj4(&grid.proj4_params).unwrap();
+        assert_eq!(projection.lat_0, 90.0);
+        assert_eq!(projection.lat_ts, 60.0);
+        assert_eq!(projection.a, 6378140.0);
+        assert_eq!(projection.to_metre, 1000.0);
+        assert!(projection.esri_wkt().contains("Stereographic_North_Pole"));
+
+        let sphere = PolarStereographic::from_proj4(
+            "+proj=stere +lat_0=90 +lon_0=0 +lat_ts=60 +a=6371000 +b=6371000",
+        )
+        .unwrap();
+        assert_eq!(sphere.inverse_flattening(), 0.0);
+        assert!(sphere
+            .esri_wkt()
+            .contains("SPHEROID[\"KNMI_Radar\",6371000,0]"));
+
+        assert!(matches!(
+            PolarStereographic::from_proj4("+proj=longlat +ellps=WGS84").unwrap_err(),
+            DatasetError::FormatError(_)
+        ));
+    }
+
+    #[test]
+    fn test_geotiff() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stack.tif");
+        let grid = GridDefinition::nl25();
+        save_geotiff(&path, grid, &[image(0), image(1)], ExportValues::MmPerHour).unwrap();
+
+        let mut decoder = Decoder::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (700, 765));
+        assert_eq!(decoder.get_tag_u32(Tag::SamplesPerPixel).unwrap(), 2);
+        let tiepoint = decoder.get_tag_f64_vec(Tag::ModelTiepointTag).unwrap();
+        assert!((tiepoint[3] + 500.0017).abs() < 0.001);
+        let geo_keys = decoder.get_tag_u16_vec(Tag::GeoKeyDirectoryTag).unwrap();
+        assert_eq!(geo_keys[3] as usize * 4 + 4, geo_keys.len());
+        let metadata = decoder.get_tag_ascii_string(Tag::Unknown(42112)).unwrap();
+        assert!(metadata.contains("2024-12-22T20:56:00Z"));
+        let nodata = decoder.get_tag_ascii_string(Tag::GdalNodata).unwrap();
+        assert_eq!(nodata, "-9999");
+        match decoder.read_image().unwrap() {
+            DecodingResult::F32(values) => assert_eq!(values[0], -9999.0),
+            _ => panic!("expected f32 values"),
+        }
+
+        let path = dir.path().join("single.tif");
+        image(0)
+            .save_geotiff(&path, grid, ExportValues::Raw)
+            .unwrap();
+        let mut decoder = Decoder::new(File::open(&path).unwrap()).unwrap();
+        match decoder.read_image().unwrap() {
+            DecodingResult::F32(values) => {
+                assert_eq!(values.len(), 700 * 765);
+                assert_eq!(values[20 * 700 + 10], 100.0);
+                assert_eq!(values[20 * 700 + 11], 0.0);
+                assert_eq!(values[0], 65535.0);
+            }
+            _ => panic!("expected f32 values"),
+        }
+
+        // pixels outside the radar range are written as the missing data value
+        let mut outside = image(0);
+        outside.calibration.out_of_image = Some(255);
+        outside.data[[0, 1]] = 255;
+        let path = dir.path().join("outside.tif");
+        outside
+            .save_geotiff(&path, grid, ExportValues::Raw)
+            .unwrap();
+        let mut decoder = Decoder::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(
+            decoder.get_tag_ascii_string(Tag::GdalNodata).unwrap(),
+            "65535"
+        );
+        match decoder.read_image().unwrap() {
+            DecodingResult::F32(values) => {
+                assert_eq!(values[0], 65535.0);
+                assert_eq!(values[1], 65535.0);
+            }
+            _ => panic!("expected f32 values"),
+        }
+
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        assert!(matches!(
+            write_geotiff(&mut buffer, grid, &[], ExportValues::Raw).unwrap_err(),
+            DatasetError::FormatError(_)
+        ));
+    }
+
+    #[test]
+    fn test_ascii_grid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.asc");
+        let grid = GridDefinition::nl25();
+        image(0)
+            .save_ascii_grid(&path, grid, ExportValues::MmPerHour)
+            .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[0], "ncols 700");
+        assert_eq!(lines[1], "nrows 765");
+        assert!(lines[4].starts_with("cellsize 1000.00"));
+        assert_eq!(lines[5], "NODATA_value -9999");
+        assert_eq!(lines.len(), 6 + 765);
+        assert_eq!(lines[6 + 20].split(' ').nth(10), Some("12"));
+        assert!(lines[6].starts_with("-9999 0 "));
+
+        let prj = std::fs::read_to_string(dir.path().join("image.prj")).unwrap();
+        assert!(prj.starts_with("PROJCS[\"KNMI_Radar_Polar_Stereographic\""));
+
+        let small = Image::new(ArrayBase::zeros((10, 10)), image(0).datetime);
+        assert!(matches!(
+            small
+                .save_ascii_grid(&path, grid, ExportValues::Raw)
+                .unwrap_err(),
+            DatasetError::FormatError(_)
+        ));
+    }
+}
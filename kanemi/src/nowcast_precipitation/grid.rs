@@ -0,0 +1,352 @@
+//! Grid definition of a KNMI radar product, read from the geographic attributes of the HDF5 file.
+//!
+//! The geographic coordinates of a pixel are calculated with
+//! - `x = (column + geo_column_offset) * geo_pixel_size_x`
+//! - `y = -(row + geo_row_offset) * geo_pixel_size_y`
+//!
+//! where `x` and `y` are the coordinates in the projection given by `projection_proj4_params`.
+
+use super::projection::{
+    GEO_BOTTOM_LEFT, GEO_BOTTOM_RIGHT, GEO_COLUMN_OFFSET, GEO_NUMBER_OF_COLUMNS,
+    GEO_NUMBER_OF_ROWS, GEO_PIXEL_SIZE_X, GEO_PIXEL_SIZE_Y, GEO_PROJ4_PARAMS, GEO_ROW_OFFSET,
+    GEO_TOP_LEFT, GEO_TOP_RIGHT,
+};
+use crate::errors::{DatasetError, ProjectionError};
+use hdf5::types::FixedAscii;
+use hdf5::Group;
+use once_cell::sync::Lazy;
+use proj4rs::proj::Proj;
+use std::fmt;
+
+/// WGS84 projection used as the source coordinate system.
+static PROJ_4326: Lazy<Proj> = Lazy::new(|| {
+    Proj::from_proj_string("+proj=longlat +ellps=WGS84 +datum=WGS84 +no_defs").unwrap()
+});
+
+/// Grid of the NL25 radar products such as the nowcast precipitation forecast.
+static GRID_NL25: Lazy<GridDefinition> = Lazy::new(|| {
+    GridDefinition::new(
+        GEO_NUMBER_OF_COLUMNS as u32,
+        GEO_NUMBER_OF_ROWS as u32,
+        GEO_PIXEL_SIZE_X,
+        GEO_PIXEL_SIZE_Y,
+        GEO_COLUMN_OFFSET,
+        GEO_ROW_OFFSET,
+        [
+            GEO_BOTTOM_LEFT,
+            GEO_TOP_LEFT,
+            GEO_TOP_RIGHT,
+            GEO_BOTTOM_RIGHT,
+        ],
+        GEO_PROJ4_PARAMS.to_string(),
+    )
+    .unwrap()
+});
+
+/// Geometry and projection of the image grid of a radar product.
+#[derive(Clone)]
+pub struct GridDefinition {
+    /// number of columns of an image
+    pub number_of_columns: u32,
+    /// number of rows of an image
+    pub number_of_rows: u32,
+    /// pixel size in km in x direction
+    pub pixel_size_x: f64,
+    /// pixel size in km in y direction
+    pub pixel_size_y: f64,
+    /// offset of the first column in pixels
+    pub column_offset: f64,
+    /// offset of the first row in pixels
+    pub row_offset: f64,
+    /// corners as (longitude, latitude): bottom left, top left, top right, bottom right
+    pub corners: [(f64, f64); 4],
+    /// proj4 string of the grid projection
+    pub proj4_params: String,
+    projection: Proj,
+}
+
+impl fmt::Debug for GridDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GridDefinition")
+            .field("number_of_columns", &self.number_of_columns)
+            .field("number_of_rows", &self.number_of_rows)
+            .field("pixel_size_x", &self.pixel_size_x)
+            .field("pixel_size_y", &self.pixel_size_y)
+            .field("column_offset", &self.column_offset)
+            .field("row_offset", &self.row_offset)
+            .field("corners", &self.corners)
+            .field("proj4_params", &self.proj4_params)
+            .finish()
+    }
+}
+
+impl GridDefinition {
+    /// Constructs a grid definition, the proj4 string is parsed to create the projection.
+    ///
+    /// # Errors
+    /// - `DatasetError::FormatError`: If the proj4 string is invalid or the grid is empty.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        number_of_columns: u32,
+        number_of_rows: u32,
+        pixel_size_x: f64,
+        pixel_size_y: f64,
+        column_offset: f64,
+        row_offset: f64,
+        corners: [(f64, f64); 4],
+        proj4_params: String,
+    ) -> Result<GridDefinition, DatasetError> {
+        if number_of_columns == 0 || number_of_rows == 0 {
+            return Err(DatasetError::FormatError(format!(
+                "Invalid grid size: {}x{}",
+                number_of_columns, number_of_rows
+            )));
+        }
+
+        if pixel_size_x == 0.0 || pixel_size_y == 0.0 {
+            return Err(DatasetError::FormatError(format!(
+                "Invalid pixel size: {}x{}",
+                pixel_size_x, pixel_size_y
+            )));
+        }
+
+        let projection = Proj::from_proj_string(&proj4_params).map_err(|e| {
+            DatasetError::FormatError(format!("Invalid proj4 params {}: {}", proj4_params, e))
+        })?;
+
+        Ok(GridDefinition {
+            number_of_columns,
+            number_of_rows,
+            pixel_size_x,
+            pixel_size_y,
+            column_offset,
+            row_offset,
+            corners,
+            proj4_params,
+            projection,
+        })
+    }
+
+    /// The grid of the NL25 radar products, used when no grid is read from a file.
+    pub fn nl25() -> &'static GridDefinition {
+        &GRID_NL25
+    }
+
+    /// Reads the grid definition from the `geographic` group of a KNMI HDF5 file.
+    ///
+    /// # Errors
+    /// - `DatasetError::FormatError`: If an attribute is missing or has an unexpected type.
+    pub fn from_hdf5(file: &Group) -> Result<GridDefinition, DatasetError> {
+        let geographic = file.group("geographic").map_err(|e| {
+            DatasetError::FormatError(format!("Error reading geographic group: {}", e))
+        })?;
+        let map_projection = geographic.group("map_projection").map_err(|e| {
+            DatasetError::FormatError(format!("Error reading map_projection group: {}", e))
+        })?;
+
+        let corners = read_f64_attr(&geographic, "geo_product_corners")?;
+        if corners.len() != 8 {
+            return Err(DatasetError::FormatError(format!(
+                "Expected 8 values for geo_product_corners, got {}",
+                corners.len()
+            )));
+        }
+
+        GridDefinition::new(
+            read_f64_attr_value(&geographic, "geo_number_columns")? as u32,
+            read_f64_attr_value(&geographic, "geo_number_rows")? as u32,
+            read_f64_attr_value(&geographic, "geo_pixel_size_x")?,
+            read_f64_attr_value(&geographic, "geo_pixel_size_y")?,
+            read_f64_attr_value(&geographic, "geo_column_offset")?,
+            read_f64_attr_value(&geographic, "geo_row_offset")?,
+            [
+                (corners[0], corners[1]),
+                (corners[2], corners[3]),
+                (corners[4], corners[5]),
+                (corners[6], corners[7]),
+            ],
+            read_string_attr(&map_projection, "projection_proj4_params")?,
+        )
+    }
+
+    /// Converts longitude and latitude coordinates to the fractional (column, row) position
+    /// on the grid, positions outside the grid are not rejected.
+    ///
+    /// # Errors
+    /// - `ProjectionError::CoordinateError`: If the coordinate transformation fails.
+    pub fn lon_lat_to_grid_position(
+        &self,
+        longitude: f64,
+        latitude: f64,
+    ) -> Result<(f64, f64), ProjectionError> {
+        let mut coordinate = (longitude.to_radians(), latitude.to_radians(), 0.0);
+
+        let proj_result =
+            proj4rs::transform::transform(&PROJ_4326, &self.projection, &mut coordinate);
+        if proj_result.is_err() {
+            return Err(ProjectionError::CoordinateError(
+                "Coordinate transformation failed".to_string(),
+            ));
+        }
+
+        let col = coordinate.0 / self.pixel_size_x.abs() - self.column_offset;
+        let row = -coordinate.1 / self.pixel_size_y.abs() - self.row_offset;
+        Ok((col, row))
+    }
+
+    /// Converts longitude and latitude coordinates to the grid and returns the grid indices
+    /// as (column, row).
+    ///
+    /// # Errors
+    /// - `ProjectionError::CoordinateError`: If the coordinate transformation fails.
+    /// - `ProjectionError::OutOfBounds`: If the resulting grid indices are outside the valid range.
+    pub fn lon_lat_to_grid(
+        &self,
+        longitude: f64,
+        latitude: f64,
+    ) -> Result<(u16, u16), ProjectionError> {
+        let (col, row) = self.lon_lat_to_grid_position(longitude, latitude)?;
+
+        // Round to the nearest integer
+        let col = col.round();
+        let row = row.round();
+
+        // Check bounds
+        if !(0.0..self.number_of_columns as f64).contains(&col)
+            || !(0.0..self.number_of_rows as f64).contains(&row)
+        {
+            return Err(ProjectionError::OutOfBounds(
+                "Coordinates are outside the valid grid boundaries".to_string(),
+            ));
+        }
+
+        Ok((col as u16, row as u16))
+    }
+
+    /// Returns the longitude and latitude coordinates for the given grid indices.
+    ///
+    /// # Errors
+    /// - `ProjectionError::CoordinateError`: If the coordinate transformation fails.
+    /// - `ProjectionError::OutOfBounds`: If the grid coordinates are outside the valid range.
+    pub fn grid_to_lon_lat(&self, col: u16, row: u16) -> Result<(f64, f64), ProjectionError> {
+        if col as u32 >= self.number_of_columns || row as u32 >= self.number_of_rows {
+            return Err(ProjectionError::OutOfBounds(
+                "Grid coordinates are outside the valid grid boundaries".to_string(),
+            ));
+        }
+
+        let x = (col as f64 + self.column_offset) * self.pixel_size_x.abs();
+        let y = -(row as f64 + self.row_offset) * self.pixel_size_y.abs();
+        let mut coordinate = (x, y, 0.0);
+
+        let proj_result =
+            proj4rs::transform::transform(&self.projection, &PROJ_4326, &mut coordinate);
+        if proj_result.is_err() {
+            return Err(ProjectionError::CoordinateError(
+                "Coordinate transformation failed".to_string(),
+            ));
+        }
+
+        Ok((coordinate.0.to_degrees(), coordinate.1.to_degrees()))
+    }
+}
+
+/// Reads a numeric attribute, scalar and array attributes are both supported.
+fn read_f64_attr(group: &Group, name: &str) -> Result<Vec<f64>, DatasetError> {
+    group
+        .attr(name)
+        .and_then(|attr| attr.read_raw::<f64>())
+        .map_err(|e| DatasetError::FormatError(format!("Error reading attribute {}: {}", name, e)))
+}
+
+/// Reads the first value of a numeric attribute.
+pub(crate) fn read_f64_attr_value(group: &Group, name: &str) -> Result<f64, DatasetError> {
+    read_f64_attr(group, name)?
+        .first()
+        .copied()
+        .ok_or_else(|| DatasetError::FormatError(format!("Attribute {} is empty", name)))
+}
+
+/// Reads a fixed length string attribute.
+pub(crate) fn read_string_attr(group: &Group, name: &str) -> Result<String, DatasetError> {
+    group
+        .attr(name)
+        .and_then(|attr| attr.read_raw::<FixedAscii<256>>())
+        .map_err(|e| DatasetError::FormatError(format!("Error reading attribute {}: {}", name, e)))?
+        .first()
+        .map(|value| value.as_str().trim().to_string())
+        .ok_or_else(|| DatasetError::FormatError(format!("Attribute {} is empty", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_grid() {
+        let grid = GridDefinition::nl25();
+        // a grid with half the resolution covering the same extent
+        let half = GridDefinition::new(
+            grid.number_of_columns / 2,
+            grid.number_of_rows / 2,
+            grid.pixel_size_x * 2.0,
+            grid.pixel_size_y * 2.0,
+            grid.column_offset / 2.0,
+            grid.row_offset / 2.0,
+            grid.corners,
+            grid.proj4_params.clone(),
+        )
+        .unwrap();
+
+        let (lon, lat) = (5.35192613, 51.71680934);
+        let (col, row) = grid.lon_lat_to_grid(lon, lat).unwrap();
+        let (half_col, half_row) = half.lon_lat_to_grid(lon, lat).unwrap();
+        assert!((col as i32 / 2 - half_col as i32).abs() <= 1);
+        assert!((row as i32 / 2 - half_row as i32).abs() <= 1);
+
+        // the last column is within a pixel of the top right corner
+        let (top_right_lon, top_right_lat) = half
+            .grid_to_lon_lat(half.number_of_columns as u16 - 1, 0)
+            .unwrap();
+        assert!((top_right_lon - grid.corners[2].0).abs() < 0.06);
+        assert!((top_right_lat - grid.corners[2].1).abs() < 0.06);
+        assert!(matches!(
+            half.grid_to_lon_lat(half.number_of_columns as u16, 0)
+                .unwrap_err(),
+            ProjectionError::OutOfBounds(_)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_grid() {
+        let grid = GridDefinition::nl25();
+        assert!(matches!(
+            GridDefinition::new(
+                0,
+                765,
+                1.0,
+                1.0,
+                0.0,
+                0.0,
+                grid.corners,
+                grid.proj4_params.clone()
+            )
+            .unwrap_err(),
+            DatasetError::FormatError(_)
+        ));
+        assert!(matches!(
+            GridDefinition::new(
+                700,
+                765,
+                1.0,
+                1.0,
+                0.0,
+                0.0,
+                grid.corners,
+                "+proj=doesnotexist".to_string()
+            )
+            .unwrap_err(),
+            DatasetError::FormatError(_)
+        ));
+    }
+}
@@ -1,5 +1,5 @@
-use super::projection::lon_lat_to_grid;
-use super::transformation::pixel_to_mm_hr;
+use super::grid::GridDefinition;
+use super::transformation::Calibration;
 use crate::errors::ProjectionError;
 use chrono::NaiveDateTime;
 use ndarray::{ArrayBase, Ix2, OwnedRepr};
@@ -11,12 +11,25 @@ use ndarray::{ArrayBase, Ix2, OwnedRepr};
 pub struct Image {
     pub data: ArrayBase<OwnedRepr<u16>, Ix2>,
     pub datetime: NaiveDateTime,
+    /// calibration used to convert the pixel values to mm/h
+    pub calibration: Calibration,
 }
 
 impl Image {
-    /// Constructs a new Image with the given pixel data and datetime.
+    /// Constructs a new Image with the given pixel data and datetime, the default
+    /// calibration is used.
     pub fn new(data: ArrayBase<OwnedRepr<u16>, Ix2>, datetime: NaiveDateTime) -> Image {
-        Image { data, datetime }
+        Image {
+            data,
+            datetime,
+            calibration: Calibration::default(),
+        }
+    }
+
+    /// Sets the calibration used to convert the pixel values to mm/h.
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = calibration;
+        self
     }
 
     /// Returns the raw pixel value at the specified x, y grid position, none if the position is out of bounds.
@@ -28,36 +41,65 @@ impl Image {
         }
     }
 
+    /// Returns the precipitation in mm/h at the specified x, y grid position, none if the position
+    /// is out of bounds or the pixel has no data.
     pub fn get_mmhhr_at_position(&self, x: usize, y: usize) -> Option<f64> {
-        self.get_value_at_position(x, y).map(pixel_to_mm_hr)
+        self.get_value_at_position(x, y)
+            .and_then(|value| self.calibration.to_mm_hr(value))
     }
 
-    /// Returns the raw pixel value at a specific longitude and latitude position, none if the position is out of bounds.
+    /// Returns the raw pixel value at a specific longitude and latitude position, projected with
+    /// the grid of the dataset the image belongs to. None if the position is out of bounds.
     ///
     /// # Errors
-    /// - `ProjectionError`: If the coordinate transformation fails or the coordinates are out of bounds.
+    /// - `ProjectionError`: If the coordinate transformation fails, the coordinates are out of bounds
+    ///   or the image does not match the size of the grid.
     pub fn get_value_at_lon_lat(
         &self,
+        grid: &GridDefinition,
         longitude: f64,
         latitude: f64,
     ) -> Result<Option<u16>, ProjectionError> {
-        let (x, y) = lon_lat_to_grid(longitude, latitude)?;
+        let shape = self.data.shape();
+        if shape[0] != grid.number_of_rows as usize || shape[1] != grid.number_of_columns as usize {
+            return Err(ProjectionError::CoordinateError(format!(
+                "Image of {}x{} pixels does not match the grid of {}x{} pixels",
+                shape[1], shape[0], grid.number_of_columns, grid.number_of_rows
+            )));
+        }
+
+        let (x, y) = grid.lon_lat_to_grid(longitude, latitude)?;
         Ok(self.get_value_at_position(x as usize, y as usize))
     }
 
+    /// Returns the precipitation in mm/h at a specific longitude and latitude position, projected
+    /// with the grid of the dataset the image belongs to. None if the pixel has no data.
+    ///
+    /// # Errors
+    /// - `ProjectionError`: If the coordinate transformation fails or the coordinates are outside the image.
     pub fn get_mmhhr_at_lon_lat(
         &self,
+        grid: &GridDefinition,
         longitude: f64,
         latitude: f64,
     ) -> Result<Option<f64>, ProjectionError> {
-        self.get_value_at_lon_lat(longitude, latitude)
-            .map(|value| value.map(pixel_to_mm_hr))
+        let value = self
+            .get_value_at_lon_lat(grid, longitude, latitude)?
+            .ok_or_else(|| {
+                ProjectionError::OutOfBounds(format!(
+                    "Coordinates {}, {} are outside the image",
+                    longitude, latitude
+                ))
+            })?;
+
+        Ok(self.calibration.to_mm_hr(value))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::nowcast_precipitation::projection::GEO_TOP_RIGHT;
     use crate::nowcast_precipitation::transformation;
 
     #[test]
@@ -93,7 +135,46 @@ mod tests {
 
         let lon = 0.0;
         let lat = 55.9736;
-        assert_eq!(image.get_value_at_lon_lat(lon, lat).unwrap().unwrap(), 0);
-        assert_eq!(image.get_mmhhr_at_lon_lat(lon, lat).unwrap().unwrap(), 0.0);
+        let grid = GridDefinition::nl25();
+        assert_eq!(
+            image.get_value_at_lon_lat(grid, lon, lat).unwrap().unwrap(),
+            0
+        );
+        assert_eq!(
+            image.get_mmhhr_at_lon_lat(grid, lon, lat).unwrap().unwrap(),
+            0.0
+        );
+
+        // an image of another size than the grid is not projected with the grid
+        let small = Image::new(ArrayBase::zeros((10, 10)), datetime);
+        assert!(matches!(
+            small.get_value_at_lon_lat(grid, lon, lat),
+            Err(ProjectionError::CoordinateError(_))
+        ));
+
+        // the right edge of the grid is outside the image
+        assert!(matches!(
+            image.get_mmhhr_at_lon_lat(grid, GEO_TOP_RIGHT.0, GEO_TOP_RIGHT.1),
+            Err(ProjectionError::OutOfBounds(_))
+        ));
+    }
+
+    #[test]
+    fn test_nodata() {
+        let data = ArrayBase::from_shape_vec((1, 3), vec![10, 65535, 7]).unwrap();
+        let datetime =
+            transformation::convert_hdf5_datetime("01-JAN-2021;20:15:00.000".to_string()).unwrap();
+        let image = Image::new(data, datetime);
+
+        assert_eq!(image.get_value_at_position(1, 0), Some(65535));
+        assert_eq!(image.get_mmhhr_at_position(0, 0), Some(1.2));
+        assert_eq!(image.get_mmhhr_at_position(1, 0), None);
+
+        let image = image.with_calibration(Calibration {
+            missing_data: Some(7),
+            ..Default::default()
+        });
+        assert_eq!(image.get_mmhhr_at_position(1, 0), None);
+        assert_eq!(image.get_mmhhr_at_position(2, 0), None);
     }
 }
@@ -1,5 +1,10 @@
+pub mod area;
 pub mod dataset;
+pub mod export;
+pub mod grid;
 pub mod image;
 pub mod models;
 pub mod projection;
+pub mod render;
+pub mod summary;
 pub mod transformation;
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AreaForecast {
+    pub datetime: String,
+    /// number of grid pixels in the area
+    pub pixel_count: usize,
+    pub values: Vec<AreaForecastValue>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AreaForecastValue {
+    pub datetime: String,
+    /// number of pixels in the area without data, these are left out of the statistics
+    pub missing_count: usize,
+    /// mean precipitation in mm/h over the pixels of the area, None when no pixel has data
+    pub mean: Option<f64>,
+    /// max precipitation in mm/h of a pixel in the area, None when no pixel has data
+    pub max: Option<f64>,
+    /// fraction of the pixels with data that have precipitation, between 0 and 1, None when
+    /// no pixel has data
+    pub coverage: Option<f64>,
+}
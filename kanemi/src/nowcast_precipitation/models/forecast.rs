@@ -9,5 +9,6 @@ pub struct PrecipitationForecast {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PrecipitationForecastValue {
     pub datetime: String,
-    pub value: f64,
+    /// precipitation in mm/h, None when the pixel has no data
+    pub value: Option<f64>,
 }
@@ -1,4 +1,8 @@
+mod area_forecast;
 mod forecast;
 
+pub use area_forecast::AreaForecast;
+pub use area_forecast::AreaForecastValue;
+
 pub use forecast::PrecipitationForecast;
 pub use forecast::PrecipitationForecastValue;
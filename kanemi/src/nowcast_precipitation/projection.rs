@@ -1,4 +1,5 @@
 //! This module contains functions to convert longitude and latitude coordinates to the HDF5 nowcast grid.
+//! The values below describe the NL25 grid, a `Dataset` reads the grid definition from the file itself.
 //!
 //! It uses the `proj4rs` crate to convert coordinates from WGS84 to the Polar stereographic projection
 //! using the proj4 string provided in the HDF5 file.
@@ -18,14 +19,25 @@
 //! - `GEO_PIXEL_SIZE_X`: 1.000003457069397
 //! - `GEO_PIXEL_SIZE_Y`: 1.000004768371582
 
+use super::grid::GridDefinition;
 use crate::errors::ProjectionError;
-use once_cell::sync::Lazy;
-use proj4rs;
-use proj4rs::proj::Proj;
 
 /// Offset for the rows in the HDF5 nowcast grid.
 pub const GEO_ROW_OFFSET: f64 = 3649.98193359375;
 
+/// Offset for the columns in the HDF5 nowcast grid.
+pub const GEO_COLUMN_OFFSET: f64 = 0.0;
+
+/// Pixel size in km in x direction.
+pub const GEO_PIXEL_SIZE_X: f64 = 1.000003457069397;
+
+/// Pixel size in km in y direction.
+pub const GEO_PIXEL_SIZE_Y: f64 = 1.000004768371582;
+
+/// Polar stereographic projection used for the HDF5 grid.
+pub const GEO_PROJ4_PARAMS: &str =
+    "+proj=stere +lat_0=90 +lon_0=0 +lat_ts=60 +a=6378.14 +b=6356.75 +x_0=0 y_0=0 +type=crs";
+
 /// Total number of rows in the grid.
 pub const GEO_NUMBER_OF_ROWS: f64 = 765.0;
 
@@ -44,20 +56,8 @@ pub const GEO_TOP_RIGHT: (f64, f64) = (10.856452941894531, 55.388973236083984);
 /// Bottom right corner of the grid.
 pub const GEO_BOTTOM_RIGHT: (f64, f64) = (9.009300231933594, 48.895301818847656);
 
-/// WGS84 projection used as the source coordinate system.
-static PROJ_4326: Lazy<Proj> = Lazy::new(|| {
-    Proj::from_proj_string("+proj=longlat +ellps=WGS84 +datum=WGS84 +no_defs").unwrap()
-});
-
-/// Polar stereographic projection used for the HDF5 grid.
-static PROJ_KNMI: Lazy<Proj> = Lazy::new(|| {
-    Proj::from_proj_string(
-        "+proj=stere +lat_0=90 +lon_0=0 +lat_ts=60 +a=6378.14 +b=6356.75 +x_0=0 y_0=0 +type=crs",
-    )
-    .unwrap()
-});
-
 /// Converts longitude and latitude coordinates to the HDF5 nowcast grid and returns the grid indices.
+/// Uses the NL25 grid, see `Dataset::grid` for the grid read from a file.
 ///
 /// # Arguments
 /// - `longitude`: Longitude in degrees.
@@ -78,27 +78,7 @@ static PROJ_KNMI: Lazy<Proj> = Lazy::new(|| {
 /// }
 /// ```
 pub fn lon_lat_to_grid(longitude: f64, latitude: f64) -> Result<(u16, u16), ProjectionError> {
-    let mut coordinate = (longitude.to_radians(), latitude.to_radians(), 0.0);
-
-    let proj_result = proj4rs::transform::transform(&PROJ_4326, &PROJ_KNMI, &mut coordinate);
-    if proj_result.is_err() {
-        return Err(ProjectionError::CoordinateError(
-            "Coordinate transformation failed".to_string(),
-        ));
-    }
-
-    // Calculate and round to the nearest integer
-    let col = coordinate.0.round();
-    let row = (-GEO_ROW_OFFSET - coordinate.1).round();
-
-    // Check bounds
-    if !(0.0..=GEO_NUMBER_OF_COLUMNS).contains(&col) || !(0.0..=GEO_NUMBER_OF_ROWS).contains(&row) {
-        return Err(ProjectionError::OutOfBounds(
-            "Coordinates are outside the valid grid boundaries".to_string(),
-        ));
-    }
-
-    Ok((col as u16, row as u16))
+    GridDefinition::nl25().lon_lat_to_grid(longitude, latitude)
 }
 
 /// Returns the longitude and latitude coordinates for the given grid indices.
@@ -110,23 +90,7 @@ pub fn lon_lat_to_grid(longitude: f64, latitude: f64) -> Result<(u16, u16), Proj
 /// - `ProjectionError::CoordinateError`: If the coordinate transformation fails.
 /// - `ProjectionError::OutOfBounds`: If the grid coordinates are outside the valid range.
 pub fn grid_to_lon_lat(col: u16, row: u16) -> Result<(f64, f64), ProjectionError> {
-    if col > GEO_NUMBER_OF_COLUMNS as u16 || row > GEO_NUMBER_OF_ROWS as u16 {
-        return Err(ProjectionError::OutOfBounds(
-            "Grid coordinates are outside the valid grid boundaries".to_string(),
-        ));
-    }
-
-    let row = -GEO_ROW_OFFSET - row as f64;
-    let mut coordinate = (col as f64, row, 0.0);
-
-    let proj_result = proj4rs::transform::transform(&PROJ_KNMI, &PROJ_4326, &mut coordinate);
-    if proj_result.is_err() {
-        return Err(ProjectionError::CoordinateError(
-            "Coordinate transformation failed".to_string(),
-        ));
-    }
-
-    Ok((coordinate.0.to_degrees(), coordinate.1.to_degrees()))
+    GridDefinition::nl25().grid_to_lon_lat(col, row)
 }
 
 #[cfg(test)]
@@ -142,39 +106,61 @@ mod tests {
         assert!((lon - GEO_TOP_LEFT.0).abs() < margin);
         assert!((lat - GEO_TOP_LEFT.1).abs() < margin);
 
-        let (lon, lat) = grid_to_lon_lat(0, GEO_NUMBER_OF_ROWS as u16).unwrap();
-        assert!((lon - GEO_BOTTOM_LEFT.0).abs() < margin);
-        assert!((lat - GEO_BOTTOM_LEFT.1).abs() < margin);
-
-        let (lon, lat) = grid_to_lon_lat(GEO_NUMBER_OF_COLUMNS as u16, 0).unwrap();
-        assert!((lon - GEO_TOP_RIGHT.0).abs() < margin);
-        assert!((lat - GEO_TOP_RIGHT.1).abs() < margin);
-
-        let (lon, lat) =
-            grid_to_lon_lat(GEO_NUMBER_OF_COLUMNS as u16, GEO_NUMBER_OF_ROWS as u16).unwrap();
-        assert!((lon - GEO_BOTTOM_RIGHT.0).abs() < margin);
-        assert!((lat - GEO_BOTTOM_RIGHT.1).abs() < margin);
+        // the last pixels are within a pixel of the other corners
+        let pixel_margin = 0.03;
+        let (lon, lat) = grid_to_lon_lat(0, GEO_NUMBER_OF_ROWS as u16 - 1).unwrap();
+        assert!((lon - GEO_BOTTOM_LEFT.0).abs() < pixel_margin);
+        assert!((lat - GEO_BOTTOM_LEFT.1).abs() < pixel_margin);
+
+        let (lon, lat) = grid_to_lon_lat(GEO_NUMBER_OF_COLUMNS as u16 - 1, 0).unwrap();
+        assert!((lon - GEO_TOP_RIGHT.0).abs() < pixel_margin);
+        assert!((lat - GEO_TOP_RIGHT.1).abs() < pixel_margin);
+
+        let (lon, lat) = grid_to_lon_lat(
+            GEO_NUMBER_OF_COLUMNS as u16 - 1,
+            GEO_NUMBER_OF_ROWS as u16 - 1,
+        )
+        .unwrap();
+        assert!((lon - GEO_BOTTOM_RIGHT.0).abs() < pixel_margin);
+        assert!((lat - GEO_BOTTOM_RIGHT.1).abs() < pixel_margin);
+
+        // the column and row count are outside the image
+        assert!(matches!(
+            grid_to_lon_lat(GEO_NUMBER_OF_COLUMNS as u16, 0).unwrap_err(),
+            ProjectionError::OutOfBounds(_)
+        ));
+        assert!(matches!(
+            grid_to_lon_lat(0, GEO_NUMBER_OF_ROWS as u16).unwrap_err(),
+            ProjectionError::OutOfBounds(_)
+        ));
     }
 
     #[test]
     fn test_lon_lat_to_grid() {
-        // Test the corners of the grid
-        assert_eq!(
-            lon_lat_to_grid(GEO_BOTTOM_LEFT.0, GEO_BOTTOM_LEFT.1).unwrap(),
-            (0, GEO_NUMBER_OF_ROWS as u16)
-        );
+        // Test the corners of the grid, the right and bottom edges are outside the image
         assert_eq!(
             lon_lat_to_grid(GEO_TOP_LEFT.0, GEO_TOP_LEFT.1).unwrap(),
             (0, 0)
         );
-        assert_eq!(
-            lon_lat_to_grid(GEO_TOP_RIGHT.0, GEO_TOP_RIGHT.1).unwrap(),
-            (GEO_NUMBER_OF_COLUMNS as u16, 0)
-        );
-        assert_eq!(
-            lon_lat_to_grid(GEO_BOTTOM_RIGHT.0, GEO_BOTTOM_RIGHT.1).unwrap(),
-            (GEO_NUMBER_OF_COLUMNS as u16, GEO_NUMBER_OF_ROWS as u16)
-        );
+        for (lon, lat) in [GEO_BOTTOM_LEFT, GEO_TOP_RIGHT, GEO_BOTTOM_RIGHT] {
+            assert!(matches!(
+                lon_lat_to_grid(lon, lat).unwrap_err(),
+                ProjectionError::OutOfBounds(_)
+            ));
+        }
+
+        // Test the last pixels of the grid
+        for (col, row) in [
+            (0, GEO_NUMBER_OF_ROWS as u16 - 1),
+            (GEO_NUMBER_OF_COLUMNS as u16 - 1, 0),
+            (
+                GEO_NUMBER_OF_COLUMNS as u16 - 1,
+                GEO_NUMBER_OF_ROWS as u16 - 1,
+            ),
+        ] {
+            let (lon, lat) = grid_to_lon_lat(col, row).unwrap();
+            assert_eq!(lon_lat_to_grid(lon, lat).unwrap(), (col, row));
+        }
 
         // Test going out of bounds by supplying coordinates outside the bbox
         assert!(matches!(
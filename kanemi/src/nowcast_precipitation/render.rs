@@ -0,0 +1,331 @@
+//! Render nowcast images to coloured radar images and animations.
+
+use super::image::Image;
+use crate::errors::DatasetError;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageBuffer, Rgba, RgbaImage};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Colour used for precipitation from `value` mm/h up to the value of the next step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrecipitationColorStep {
+    pub value: f64,
+    pub color: [u8; 4],
+}
+
+/// Precipitation colour scale, pixels with less precipitation than the first step are transparent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorScale {
+    steps: Vec<PrecipitationColorStep>,
+}
+
+impl Default for ColorScale {
+    fn default() -> Self {
+        let steps = [
+            (0.1, [155, 200, 255, 255]),
+            (0.5, [90, 150, 240, 255]),
+            (1.0, [30, 90, 220, 255]),
+            (2.0, [0, 170, 60, 255]),
+            (5.0, [250, 230, 0, 255]),
+            (10.0, [255, 140, 0, 255]),
+            (20.0, [230, 0, 0, 255]),
+            (50.0, [160, 0, 160, 255]),
+        ];
+
+        ColorScale::new(
+            steps
+                .into_iter()
+                .map(|(value, color)| PrecipitationColorStep { value, color })
+                .collect(),
+        )
+    }
+}
+
+impl ColorScale {
+    /// Creates a colour scale from the steps, the steps are sorted by value.
+    pub fn new(mut steps: Vec<PrecipitationColorStep>) -> Self {
+        steps.sort_by(|a, b| a.value.total_cmp(&b.value));
+        ColorScale { steps }
+    }
+
+    pub fn steps(&self) -> &[PrecipitationColorStep] {
+        &self.steps
+    }
+
+    /// Returns the colour for the precipitation in mm/h, transparent below the first step.
+    pub fn color(&self, value: f64) -> Rgba<u8> {
+        self.steps
+            .iter()
+            .rev()
+            .find(|step| value >= step.value)
+            .map(|step| Rgba(step.color))
+            .unwrap_or(Rgba([0, 0, 0, 0]))
+    }
+}
+
+/// Options used to render nowcast images.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub color_scale: ColorScale,
+    /// every grid pixel is rendered as a block of `upscale_factor` by `upscale_factor` pixels
+    pub upscale_factor: u32,
+    /// draw the datetime of the image in the top left corner
+    pub timestamp: bool,
+    /// time every frame of an animation is shown
+    pub frame_delay: Duration,
+    /// colour of pixels without data, transparent by default
+    pub nodata_color: [u8; 4],
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            color_scale: ColorScale::default(),
+            upscale_factor: 1,
+            timestamp: true,
+            frame_delay: Duration::from_millis(500),
+            nodata_color: [0, 0, 0, 0],
+        }
+    }
+}
+
+impl Image {
+    /// Renders the image with the colour scale of the options, areas without rain are transparent
+    /// and pixels without data get the nodata colour of the options.
+    pub fn render(&self, options: &RenderOptions) -> RgbaImage {
+        let scale = options.upscale_factor.max(1);
+        let height = self.data.shape()[0] as u32;
+        let width = self.data.shape()[1] as u32;
+
+        let mut img = ImageBuffer::from_fn(width * scale, height * scale, |x, y| {
+            let value = self.data[[(y / scale) as usize, (x / scale) as usize]];
+            match self.calibration.to_mm_hr(value) {
+                Some(value) => options.color_scale.color(value),
+                None => Rgba(options.nodata_color),
+            }
+        });
+
+        if options.timestamp {
+            let text = self.datetime.format("%Y-%m-%d %H:%M UTC").to_string();
+            draw_label(&mut img, &text, scale * 2);
+        }
+
+        img
+    }
+
+    /// Renders the image and saves it as PNG.
+    ///
+    /// # Errors
+    /// - `DatasetError::WriteError`: If the image can not be written.
+    pub fn save_png(
+        &self,
+        path: impl AsRef<Path>,
+        options: &RenderOptions,
+    ) -> Result<(), DatasetError> {
+        let path = path.as_ref();
+        self.render(options)
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| {
+                DatasetError::WriteError(format!("Failed to write {}: {}", path.display(), e))
+            })
+    }
+}
+
+/// Renders the images as frames of an animated GIF which repeats forever.
+///
+/// # Errors
+/// - `DatasetError::WriteError`: If the animation can not be written.
+pub fn write_animation<W: Write>(
+    writer: W,
+    images: &[Image],
+    options: &RenderOptions,
+) -> Result<(), DatasetError> {
+    let write_error = |e: image::ImageError| {
+        DatasetError::WriteError(format!("Failed to write animation: {}", e))
+    };
+
+    let delay = Delay::from_saturating_duration(options.frame_delay);
+    let mut encoder = GifEncoder::new_with_speed(writer, 10);
+    encoder.set_repeat(Repeat::Infinite).map_err(write_error)?;
+    for image in images {
+        let frame = Frame::from_parts(image.render(options), 0, 0, delay);
+        encoder.encode_frame(frame).map_err(write_error)?;
+    }
+
+    Ok(())
+}
+
+/// Renders the images as animated GIF and saves it at the path.
+///
+/// # Errors
+/// - `DatasetError::WriteError`: If the animation can not be written.
+pub fn save_animation(
+    path: impl AsRef<Path>,
+    images: &[Image],
+    options: &RenderOptions,
+) -> Result<(), DatasetError> {
+    let path = path.as_ref();
+    let file = File::create(path).map_err(|e| {
+        DatasetError::WriteError(format!("Failed to create {}: {}", path.display(), e))
+    })?;
+
+    write_animation(BufWriter::new(file), images, options)
+}
+
+/// Glyphs of 3x5 pixels, every row is 3 bits with the leftmost pixel as highest bit.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [7, 1, 7, 4, 7],
+        '3' => [7, 1, 7, 1, 7],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 7, 1, 7],
+        '6' => [7, 4, 7, 5, 7],
+        '7' => [7, 1, 1, 1, 1],
+        '8' => [7, 5, 7, 5, 7],
+        '9' => [7, 5, 7, 1, 7],
+        ':' => [0, 2, 0, 2, 0],
+        '-' => [0, 0, 7, 0, 0],
+        'U' => [5, 5, 5, 5, 7],
+        'T' => [7, 2, 2, 2, 2],
+        'C' => [7, 4, 4, 4, 7],
+        _ => [0; 5],
+    }
+}
+
+/// Draw white text on a dark background in the top left corner of the image, every
+/// pixel of a glyph is drawn as a block of `scale` pixels.
+fn draw_label(img: &mut RgbaImage, text: &str, scale: u32) {
+    let padding = scale * 2;
+    let char_width = 4 * scale;
+    let label_width = padding * 2 + char_width * text.chars().count() as u32 - scale;
+    let label_height = padding * 2 + 5 * scale;
+
+    for y in 0..label_height.min(img.height()) {
+        for x in 0..label_width.min(img.width()) {
+            img.put_pixel(x, y, Rgba([30, 30, 30, 200]));
+        }
+    }
+
+    for (i, c) in text.chars().enumerate() {
+        let left = padding + i as u32 * char_width;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..3 {
+                if bits & (4 >> col) == 0 {
+                    continue;
+                }
+
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = left + col * scale + dx;
+                        let y = padding + row as u32 * scale + dy;
+                        if x < img.width() && y < img.height() {
+                            img.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use image::AnimationDecoder;
+    use ndarray::ArrayBase;
+
+    fn image(value: u16) -> Image {
+        let data = ArrayBase::from_shape_fn((40, 60), |(y, _)| if y < 20 { 0 } else { value });
+        let datetime = NaiveDate::from_ymd_opt(2024, 12, 22)
+            .unwrap()
+            .and_hms_opt(20, 55, 0)
+            .unwrap();
+        Image::new(data, datetime)
+    }
+
+    #[test]
+    fn test_color_scale() {
+        let scale = ColorScale::default();
+        assert_eq!(scale.color(0.0), Rgba([0, 0, 0, 0]));
+        assert_eq!(scale.color(0.05), Rgba([0, 0, 0, 0]));
+        assert_eq!(scale.color(0.1), Rgba([155, 200, 255, 255]));
+        assert_eq!(scale.color(3.0), Rgba([0, 170, 60, 255]));
+        assert_eq!(scale.color(500.0), Rgba([160, 0, 160, 255]));
+
+        let scale = ColorScale::new(vec![
+            PrecipitationColorStep {
+                value: 5.0,
+                color: [255, 0, 0, 255],
+            },
+            PrecipitationColorStep {
+                value: 1.0,
+                color: [0, 0, 255, 128],
+            },
+        ]);
+        assert_eq!(scale.steps()[0].value, 1.0);
+        assert_eq!(scale.color(2.0), Rgba([0, 0, 255, 128]));
+    }
+
+    #[test]
+    fn test_render() {
+        // 10 = 1.2 mm/h
+        let image = image(10);
+        let options = RenderOptions {
+            upscale_factor: 2,
+            ..Default::default()
+        };
+        let img = image.render(&options);
+        assert_eq!(img.dimensions(), (120, 80));
+        // timestamp label
+        assert_eq!(img.get_pixel(1, 1), &Rgba([30, 30, 30, 200]));
+        // no rain is transparent
+        assert_eq!(img.get_pixel(100, 38)[3], 0);
+        assert_eq!(img.get_pixel(100, 60), &Rgba([30, 90, 220, 255]));
+
+        let options = RenderOptions {
+            timestamp: false,
+            ..Default::default()
+        };
+        assert_eq!(image.render(&options).get_pixel(1, 1)[3], 0);
+
+        let options = RenderOptions {
+            timestamp: false,
+            nodata_color: [128, 128, 128, 255],
+            ..Default::default()
+        };
+        let img = self::image(65535).render(&options);
+        assert_eq!(img.get_pixel(10, 30), &Rgba([128, 128, 128, 255]));
+        assert_eq!(img.get_pixel(10, 10)[3], 0);
+    }
+
+    #[test]
+    fn test_save_png_and_animation() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = RenderOptions::default();
+
+        let png_path = dir.path().join("frame.png");
+        image(10).save_png(&png_path, &options).unwrap();
+        assert_eq!(image::open(&png_path).unwrap().width(), 60);
+
+        let gif_path = dir.path().join("animation.gif");
+        save_animation(&gif_path, &[image(10), image(50), image(100)], &options).unwrap();
+        let decoder = image::codecs::gif::GifDecoder::new(std::io::BufReader::new(
+            File::open(&gif_path).unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(decoder.into_frames().count(), 3);
+
+        assert!(matches!(
+            image(10)
+                .save_png(dir.path().join("missing/frame.png"), &options)
+                .unwrap_err(),
+            DatasetError::WriteError(_)
+        ));
+    }
+}
@@ -0,0 +1,351 @@
+//! Summary of a precipitation forecast, when the rain starts and stops and how much rain is expected.
+
+use super::models::{PrecipitationForecast, PrecipitationForecastValue};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Minutes between the images of the nowcast dataset, used when the time between the
+/// forecast values can not be determined.
+const DEFAULT_STEP_MINUTES: i64 = 5;
+
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Intensity class of precipitation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Intensity {
+    Light,
+    Moderate,
+    Heavy,
+}
+
+impl fmt::Display for Intensity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Intensity::Light => write!(f, "light"),
+            Intensity::Moderate => write!(f, "moderate"),
+            Intensity::Heavy => write!(f, "heavy"),
+        }
+    }
+}
+
+/// Thresholds in mm/h used to classify the intensity of precipitation, rain below
+/// `moderate` is light and rain from `heavy` is heavy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntensityThresholds {
+    pub moderate: f64,
+    pub heavy: f64,
+}
+
+impl Default for IntensityThresholds {
+    fn default() -> Self {
+        IntensityThresholds {
+            moderate: 2.5,
+            heavy: 10.0,
+        }
+    }
+}
+
+impl IntensityThresholds {
+    /// Classify the precipitation in mm/h.
+    pub fn classify(&self, value: f64) -> Intensity {
+        if value >= self.heavy {
+            Intensity::Heavy
+        } else if value >= self.moderate {
+            Intensity::Moderate
+        } else {
+            Intensity::Light
+        }
+    }
+}
+
+/// Summary of a precipitation forecast. The start and end are of the first period with rain,
+/// the peak and total are of the whole forecast. Values without data are unknown, they are
+/// skipped and mentioned in the description.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ForecastSummary {
+    /// true when rain is expected somewhere in the forecast
+    pub rain_expected: bool,
+    /// datetime of the first value with rain
+    pub start: Option<String>,
+    /// minutes from the start of the forecast until the rain starts, 0 when it rains now
+    pub starts_in_minutes: Option<i64>,
+    /// datetime of the first dry value after the start, None when it does not stop raining
+    /// within the forecast
+    pub end: Option<String>,
+    /// minutes of rain, until the end of the forecast when it does not stop raining
+    pub duration_minutes: Option<i64>,
+    /// highest precipitation in mm/h
+    pub peak: f64,
+    /// datetime of the highest precipitation
+    pub peak_datetime: Option<String>,
+    /// accumulated precipitation in mm
+    pub total: f64,
+    /// intensity of the peak precipitation
+    pub intensity: Option<Intensity>,
+    /// number of values without data, these are not included in the summary
+    pub missing_values: usize,
+    /// human-readable summary, for example
+    /// "Light rain starting in 15 minutes, lasting 40 minutes, 3.2 mm total"
+    pub description: String,
+}
+
+impl PrecipitationForecast {
+    /// Summarise the forecast with the default intensity thresholds.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kanemi::nowcast_precipitation::dataset::Dataset;
+    /// let dataset = Dataset::new("../example_data/RAD_NL25_RAC_FM_202412222055.h5".to_string()).unwrap();
+    /// let forecast = dataset.get_forecast(5.0, 52.0).unwrap();
+    /// println!("{}", forecast.summary().description);
+    /// ```
+    pub fn summary(&self) -> ForecastSummary {
+        self.summary_with_thresholds(&IntensityThresholds::default())
+    }
+
+    /// Summarise the forecast, the intensity of the peak precipitation is classified with
+    /// the given thresholds.
+    pub fn summary_with_thresholds(&self, thresholds: &IntensityThresholds) -> ForecastSummary {
+        let rain = |value: &PrecipitationForecastValue| value.value.unwrap_or_default();
+        let is_rain = |value: &PrecipitationForecastValue| value.value.is_some_and(|v| v > 0.0);
+        let is_dry = |value: &PrecipitationForecastValue| value.value.is_some_and(|v| v <= 0.0);
+        let missing_values = self
+            .values
+            .iter()
+            .filter(|value| value.value.is_none())
+            .count();
+        let times: Vec<Option<NaiveDateTime>> = self
+            .values
+            .iter()
+            .map(|value| NaiveDateTime::parse_from_str(&value.datetime, DATETIME_FORMAT).ok())
+            .collect();
+
+        let step = match (times.first(), times.get(1)) {
+            (Some(Some(first)), Some(Some(second))) if second > first => {
+                (*second - *first).num_minutes()
+            }
+            _ => DEFAULT_STEP_MINUTES,
+        };
+        // minutes since the first value, based on the datetime when it can be parsed
+        let minutes = |index: usize| match (times[0], times[index]) {
+            (Some(first), Some(time)) => (time - first).num_minutes(),
+            _ => index as i64 * step,
+        };
+
+        // values without data are not included in the total
+        let total = self.values.iter().map(rain).sum::<f64>() * step as f64 / 60.0;
+        let total = (total * 100.0).round() / 100.0;
+        // first value with the highest precipitation
+        let peak = self.values.iter().filter(|value| is_rain(value)).fold(
+            None,
+            |peak: Option<&PrecipitationForecastValue>, value| match peak {
+                Some(peak) if rain(peak) >= rain(value) => Some(peak),
+                _ => Some(value),
+            },
+        );
+
+        let start_index = self.values.iter().position(is_rain);
+        // the rain only ends at a dry value, values without data do not end it
+        let end_index = start_index.and_then(|start| {
+            self.values[start..]
+                .iter()
+                .position(is_dry)
+                .map(|offset| start + offset)
+        });
+
+        let starts_in_minutes = start_index.map(minutes);
+        let duration_minutes = start_index.map(|start| match end_index {
+            Some(end) => minutes(end) - minutes(start),
+            None => minutes(self.values.len() - 1) + step - minutes(start),
+        });
+        let intensity = peak.map(|peak| thresholds.classify(rain(peak)));
+
+        let description = match (intensity, starts_in_minutes, duration_minutes) {
+            (Some(intensity), Some(starts_in), Some(duration)) => {
+                let intensity = capitalize(&intensity.to_string());
+                let start = match starts_in {
+                    0 => "now".to_string(),
+                    _ => format!("starting in {} minutes", starts_in),
+                };
+                let duration = match end_index {
+                    Some(_) => format!("lasting {} minutes", duration),
+                    None => format!("lasting at least {} minutes", duration),
+                };
+                format!(
+                    "{} rain {}, {}, {:.1} mm total",
+                    intensity, start, duration, total
+                )
+            }
+            _ if missing_values == self.values.len() => format!(
+                "No data available for the next {} minutes",
+                self.values.len() as i64 * step
+            ),
+            _ => format!(
+                "No rain expected in the next {} minutes",
+                self.values.len() as i64 * step
+            ),
+        };
+        let description = match missing_values {
+            0 => description,
+            _ if missing_values == self.values.len() => description,
+            _ => format!(
+                "{} ({} of {} values missing)",
+                description,
+                missing_values,
+                self.values.len()
+            ),
+        };
+
+        ForecastSummary {
+            rain_expected: start_index.is_some(),
+            start: start_index.map(|index| self.values[index].datetime.clone()),
+            starts_in_minutes,
+            end: end_index.map(|index| self.values[index].datetime.clone()),
+            duration_minutes,
+            peak: peak.map(rain).unwrap_or_default(),
+            peak_datetime: peak.map(|peak| peak.datetime.clone()),
+            total,
+            intensity,
+            missing_values,
+            description,
+        }
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forecast(values: &[f64]) -> PrecipitationForecast {
+        forecast_with_nodata(&values.iter().map(|&value| Some(value)).collect::<Vec<_>>())
+    }
+
+    fn forecast_with_nodata(values: &[Option<f64>]) -> PrecipitationForecast {
+        let values = values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| PrecipitationForecastValue {
+                datetime: format!("2024-12-22T{:02}:{:02}:00Z", 21 + i / 12, (i % 12) * 5),
+                value,
+            })
+            .collect();
+
+        PrecipitationForecast {
+            datetime: "2024-12-22T21:00:00Z".to_string(),
+            values,
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut values = vec![0.0; 25];
+        values[3..11].copy_from_slice(&[0.6, 1.2, 2.4, 4.8, 6.0, 3.6, 1.2, 0.6]);
+        let summary = forecast(&values).summary();
+
+        assert!(summary.rain_expected);
+        assert_eq!(summary.start.as_deref(), Some("2024-12-22T21:15:00Z"));
+        assert_eq!(summary.starts_in_minutes, Some(15));
+        assert_eq!(summary.end.as_deref(), Some("2024-12-22T21:55:00Z"));
+        assert_eq!(summary.duration_minutes, Some(40));
+        assert_eq!(summary.peak, 6.0);
+        assert_eq!(
+            summary.peak_datetime.as_deref(),
+            Some("2024-12-22T21:35:00Z")
+        );
+        assert_eq!(summary.total, 1.7);
+        assert_eq!(summary.intensity, Some(Intensity::Moderate));
+        assert_eq!(
+            summary.description,
+            "Moderate rain starting in 15 minutes, lasting 40 minutes, 1.7 mm total"
+        );
+
+        let thresholds = IntensityThresholds {
+            moderate: 8.0,
+            heavy: 20.0,
+        };
+        let summary = forecast(&values).summary_with_thresholds(&thresholds);
+        assert_eq!(summary.intensity, Some(Intensity::Light));
+    }
+
+    #[test]
+    fn test_summary_rain_now_and_dry() {
+        let summary = forecast(&[12.0; 25]).summary();
+        assert_eq!(summary.starts_in_minutes, Some(0));
+        assert_eq!(summary.end, None);
+        assert_eq!(summary.duration_minutes, Some(125));
+        assert_eq!(
+            summary.peak_datetime.as_deref(),
+            Some("2024-12-22T21:00:00Z")
+        );
+        assert_eq!(summary.intensity, Some(Intensity::Heavy));
+        assert_eq!(
+            summary.description,
+            "Heavy rain now, lasting at least 125 minutes, 25.0 mm total"
+        );
+
+        let summary = forecast(&[0.0; 25]).summary();
+        assert!(!summary.rain_expected);
+        assert_eq!(summary.start, None);
+        assert_eq!(summary.intensity, None);
+        assert_eq!(summary.total, 0.0);
+        assert_eq!(
+            summary.description,
+            "No rain expected in the next 125 minutes"
+        );
+
+        let mut values = vec![Some(1.2); 25];
+        values[0] = None;
+        values[5] = None;
+        let summary = forecast_with_nodata(&values).summary();
+        assert_eq!(summary.starts_in_minutes, Some(5));
+        assert_eq!(summary.end, None);
+        assert_eq!(summary.duration_minutes, Some(120));
+        assert_eq!(summary.total, 2.3);
+        assert_eq!(summary.missing_values, 2);
+        assert_eq!(
+            summary.description,
+            "Light rain starting in 5 minutes, lasting at least 120 minutes, 2.3 mm total \
+             (2 of 25 values missing)"
+        );
+    }
+
+    #[test]
+    fn test_summary_nodata() {
+        // values without data are skipped for the peak and the end of the rain
+        let values = [Some(0.0), None, Some(0.6), None, Some(1.2), Some(0.0), None];
+        let summary = forecast_with_nodata(&values).summary();
+        assert_eq!(summary.starts_in_minutes, Some(10));
+        assert_eq!(summary.end.as_deref(), Some("2024-12-22T21:25:00Z"));
+        assert_eq!(summary.duration_minutes, Some(15));
+        assert_eq!(summary.peak, 1.2);
+        assert_eq!(
+            summary.peak_datetime.as_deref(),
+            Some("2024-12-22T21:20:00Z")
+        );
+
+        let summary = forecast_with_nodata(&[Some(0.0), None, Some(0.0)]).summary();
+        assert!(!summary.rain_expected);
+        assert_eq!(
+            summary.description,
+            "No rain expected in the next 15 minutes (1 of 3 values missing)"
+        );
+
+        let summary = forecast_with_nodata(&[None; 3]).summary();
+        assert!(!summary.rain_expected);
+        assert_eq!(summary.missing_values, 3);
+        assert_eq!(
+            summary.description,
+            "No data available for the next 15 minutes"
+        );
+    }
+}
@@ -5,18 +5,65 @@ use chrono::NaiveDateTime;
 /// The calibration factor used to convert pixel values to millimeters per hour (mm/hr).
 const CALIBRATION_FACTOR: f64 = 0.01;
 
+/// Pixel value used by KNMI for pixels without data and pixels outside the radar range.
+pub const NODATA_VALUE: u16 = 65535;
+
+/// Calibration of the raw pixel values of an image, read from the `calibration` group of
+/// the image in the HDF5 file. The precipitation in mm per image (5 minutes) is
+/// `gain * value + offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    pub gain: f64,
+    pub offset: f64,
+    /// pixel value of pixels without data
+    pub missing_data: Option<u16>,
+    /// pixel value of pixels outside the radar range
+    pub out_of_image: Option<u16>,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration {
+            gain: CALIBRATION_FACTOR,
+            offset: 0.0,
+            missing_data: Some(NODATA_VALUE),
+            out_of_image: Some(NODATA_VALUE),
+        }
+    }
+}
+
+impl Calibration {
+    /// Returns true if the pixel value has no data or is outside the radar range.
+    pub fn is_missing(&self, value: u16) -> bool {
+        self.missing_data == Some(value) || self.out_of_image == Some(value)
+    }
+
+    /// Converts a pixel value to millimeters per hour (mm/hr), rounded to two decimal places.
+    /// Returns None for pixels without data.
+    pub fn to_mm_hr(&self, value: u16) -> Option<f64> {
+        if self.is_missing(value) {
+            return None;
+        }
+
+        let result = (value as f64 * self.gain + self.offset) * 12.0;
+        Some((result * 100.0).round() / 100.0)
+    }
+}
+
 /// Converts a pixel value to millimeters per hour (mm/hr).
 ///
-/// This function takes an 8-bit unsigned integer (`u8`) representing a pixel value,
-/// applies a calibration factor, and converts it to a precipitation rate in millimeters per hour (mm/hr).
+/// This function takes a 16-bit unsigned integer (`u16`) representing a pixel value,
+/// applies the default calibration, and converts it to a precipitation rate in millimeters per hour (mm/hr).
+/// Use `Calibration::to_mm_hr` to convert with the calibration read from the file.
 ///
 /// # Arguments
 ///
-/// * `value` - An 8-bit unsigned integer representing the pixel value.
+/// * `value` - A 16-bit unsigned integer representing the pixel value.
 ///
 /// # Returns
 ///
-/// * `f64` - The precipitation rate in millimeters per hour (mm/hr), rounded to two decimal places.
+/// * `Option<f64>` - The precipitation rate in millimeters per hour (mm/hr), rounded to two decimal places.
+///   None for the nodata value 65535.
 ///
 /// # Examples
 ///
@@ -26,10 +73,23 @@ const CALIBRATION_FACTOR: f64 = 0.01;
 /// let mm_hr = pixel_to_mm_hr(113);
 /// let mm_hr = pixel_to_mm_hr(31);
 /// let mm_hr = pixel_to_mm_hr(0);
+/// assert_eq!(pixel_to_mm_hr(65535), None);
 /// ```
-pub fn pixel_to_mm_hr(value: u16) -> f64 {
-    let result = (value as f64 * CALIBRATION_FACTOR) * 12.0;
-    (result * 100.0).round() / 100.0
+pub fn pixel_to_mm_hr(value: u16) -> Option<f64> {
+    Calibration::default().to_mm_hr(value)
+}
+
+/// Parses a KNMI calibration formula such as `GEO=0.010000*PV+0.000000` and returns the
+/// gain and offset.
+pub fn parse_calibration_formula(formula: &str) -> Option<(f64, f64)> {
+    let formula: String = formula.chars().filter(|c| !c.is_whitespace()).collect();
+    let (gain, offset) = formula.strip_prefix("GEO=")?.split_once("*PV")?;
+    let offset = match offset {
+        "" => 0.0,
+        offset => offset.strip_prefix('+').unwrap_or(offset).parse().ok()?,
+    };
+
+    Some((gain.parse().ok()?, offset))
 }
 
 /// Converts a HDF5 formatted datetime string to a chrono `NaiveDateTime`.
@@ -45,9 +105,35 @@ mod tests {
 
     #[test]
     fn test_pixel_value_to_mm_hr() {
-        assert_eq!(pixel_to_mm_hr(113), 13.56);
-        assert_eq!(pixel_to_mm_hr(31), 3.72);
-        assert_eq!(pixel_to_mm_hr(0), 0.00);
+        assert_eq!(pixel_to_mm_hr(113), Some(13.56));
+        assert_eq!(pixel_to_mm_hr(31), Some(3.72));
+        assert_eq!(pixel_to_mm_hr(0), Some(0.00));
+        assert_eq!(pixel_to_mm_hr(NODATA_VALUE), None);
+    }
+
+    #[test]
+    fn test_calibration() {
+        assert_eq!(
+            parse_calibration_formula("GEO=0.010000*PV+0.000000"),
+            Some((0.01, 0.0))
+        );
+        assert_eq!(
+            parse_calibration_formula("GEO = 0.5 * PV - 32"),
+            Some((0.5, -32.0))
+        );
+        assert_eq!(parse_calibration_formula("GEO=0.02*PV"), Some((0.02, 0.0)));
+        assert_eq!(parse_calibration_formula("DBZ=0.5*PV-32"), None);
+
+        let calibration = Calibration {
+            gain: 0.02,
+            offset: 0.0,
+            missing_data: Some(0),
+            out_of_image: Some(255),
+        };
+        assert_eq!(calibration.to_mm_hr(10), Some(2.4));
+        assert_eq!(calibration.to_mm_hr(0), None);
+        assert_eq!(calibration.to_mm_hr(255), None);
+        assert_eq!(calibration.to_mm_hr(NODATA_VALUE), Some(15728.4));
     }
 
     #[test]
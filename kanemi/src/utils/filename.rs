@@ -0,0 +1,64 @@
+use chrono::{NaiveDateTime, NaiveTime};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Matches the last block of 10 (yyyymmddHH) or 12 (yyyymmddHHMM) digits in a filename
+static TIMESTAMP_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|[^0-9])(\d{12}|\d{10})(?:[^0-9]|$)").unwrap());
+
+/// Extract the timestamp from a KNMI filename, KNMI filenames contain the (UTC) time the
+/// file is valid for, for example:
+/// - `RAD_NL25_RAC_FM_202412222055.h5`: 2024-12-22 20:55
+/// - `KMDS__OPER_P___10M_OBS_L2_202412201930.nc`: 2024-12-20 19:30
+/// - `HARM43_V1_P1_2024122218.tar`: 2024-12-22 18:00
+///
+/// Returns None if no timestamp can be found.
+pub fn timestamp_from_filename(filename: &str) -> Option<NaiveDateTime> {
+    let digits = TIMESTAMP_REGEX
+        .captures_iter(filename)
+        .last()
+        .and_then(|caps| caps.get(1))?
+        .as_str();
+
+    match digits.len() {
+        12 => NaiveDateTime::parse_from_str(digits, "%Y%m%d%H%M").ok(),
+        _ => {
+            let date = chrono::NaiveDate::parse_from_str(&digits[0..8], "%Y%m%d").ok()?;
+            let hour = digits[8..10].parse::<u32>().ok()?;
+            Some(date.and_time(NaiveTime::from_hms_opt(hour, 0, 0)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_timestamp_from_filename() {
+        assert_eq!(
+            timestamp_from_filename("RAD_NL25_RAC_FM_202412222055.h5"),
+            NaiveDate::from_ymd_opt(2024, 12, 22)
+                .unwrap()
+                .and_hms_opt(20, 55, 0)
+        );
+        assert_eq!(
+            timestamp_from_filename("KMDS__OPER_P___10M_OBS_L2_202412201930.nc"),
+            NaiveDate::from_ymd_opt(2024, 12, 20)
+                .unwrap()
+                .and_hms_opt(19, 30, 0)
+        );
+        assert_eq!(
+            timestamp_from_filename("HARM43_V1_P1_2024122218.tar"),
+            NaiveDate::from_ymd_opt(2024, 12, 22)
+                .unwrap()
+                .and_hms_opt(18, 0, 0)
+        );
+        assert_eq!(timestamp_from_filename("no_timestamp.h5"), None);
+        assert_eq!(
+            timestamp_from_filename("RAD_NL25_RAC_FM_209913992055.h5"),
+            None
+        );
+    }
+}
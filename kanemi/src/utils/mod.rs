@@ -1,4 +1,6 @@
 mod calculations;
+mod filename;
 
 pub use calculations::haversine_distance;
 pub use calculations::to_radians;
+pub use filename::timestamp_from_filename;
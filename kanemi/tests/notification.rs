@@ -0,0 +1,481 @@
+use bytes::BytesMut;
+use futures::StreamExt;
+use kanemi::dataplatform::models::config::{
+    BackoffPolicy, DatasetConfig, KnownDataset, MqttConfig, MqttTransport,
+};
+use kanemi::dataplatform::notification::{
+    ConnectionState, NotificationEvent, NotificationService, NotificationStream,
+};
+use kanemi::dataplatform::store::NotificationStore;
+use kanemi::errors::NotificationError;
+use rumqttc::{
+    mqttbytes::{self, v4},
+    ConnectReturnCode, QoS, SubscribeReasonCode,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+const MAX_PACKET_SIZE: usize = 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Minimal in-process MQTT broker, every message is published to a client once it
+/// subscribed to a topic filter matching the message topic. Subscriptions to the rejected
+/// topic filters fail.
+struct Broker {
+    port: u16,
+    connects: mpsc::UnboundedReceiver<v4::Connect>,
+}
+
+impl Broker {
+    /// Start the broker, the first `drop_connections` connections are closed without a
+    /// reply to simulate a broker which is not available.
+    async fn start(messages: Vec<(String, String)>, drop_connections: usize) -> Broker {
+        Broker::start_rejecting(messages, drop_connections, Vec::new()).await
+    }
+
+    async fn start_rejecting(
+        messages: Vec<(String, String)>,
+        drop_connections: usize,
+        rejected: Vec<String>,
+    ) -> Broker {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (connects_sender, connects) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut connections = 0;
+            while let Ok((socket, _)) = listener.accept().await {
+                connections += 1;
+                if connections <= drop_connections {
+                    drop(socket);
+                    continue;
+                }
+
+                tokio::spawn(handle_connection(
+                    socket,
+                    messages.clone(),
+                    rejected.clone(),
+                    connects_sender.clone(),
+                ));
+            }
+        });
+
+        Broker { port, connects }
+    }
+
+    fn config(&self, subscriptions: Vec<DatasetConfig>) -> MqttConfig {
+        let mut subscriptions = subscriptions.into_iter();
+        let mut config = MqttConfig::new(
+            "api-key".to_string(),
+            subscriptions.next().unwrap(),
+            "127.0.0.1".to_string(),
+            self.port,
+            "dataplatform/file/v1".to_string(),
+        )
+        .with_transport(MqttTransport::Tcp)
+        .with_reconnect_policy(BackoffPolicy {
+            initial_delay: Duration::from_millis(10),
+            jitter: 0.0,
+            max_retries: Some(3),
+            ..Default::default()
+        });
+
+        for dataset in subscriptions {
+            config = config.with_dataset(dataset);
+        }
+
+        config
+    }
+}
+
+async fn handle_connection(
+    mut socket: TcpStream,
+    messages: Vec<(String, String)>,
+    rejected: Vec<String>,
+    connects: mpsc::UnboundedSender<v4::Connect>,
+) {
+    let mut buffer = BytesMut::new();
+
+    loop {
+        let packet = match v4::read(&mut buffer, MAX_PACKET_SIZE) {
+            Ok(packet) => packet,
+            Err(mqttbytes::Error::InsufficientBytes(_)) => match socket.read_buf(&mut buffer).await
+            {
+                Ok(0) | Err(_) => return,
+                Ok(_) => continue,
+            },
+            Err(_) => return,
+        };
+
+        let mut reply = BytesMut::new();
+        match packet {
+            v4::Packet::Connect(connect) => {
+                let _ = connects.send(connect);
+                v4::ConnAck::new(ConnectReturnCode::Success, false)
+                    .write(&mut reply)
+                    .unwrap();
+            }
+            v4::Packet::Subscribe(subscribe) => {
+                let return_codes = subscribe
+                    .filters
+                    .iter()
+                    .map(|filter| {
+                        if rejected.contains(&filter.path) {
+                            SubscribeReasonCode::Failure
+                        } else {
+                            SubscribeReasonCode::Success(filter.qos)
+                        }
+                    })
+                    .collect();
+                v4::SubAck::new(subscribe.pkid, return_codes)
+                    .write(&mut reply)
+                    .unwrap();
+
+                for (topic, payload) in &messages {
+                    let subscribed = subscribe.filters.iter().any(|filter| {
+                        !rejected.contains(&filter.path) && rumqttc::matches(topic, &filter.path)
+                    });
+                    if subscribed {
+                        v4::Publish::new(topic, QoS::AtMostOnce, payload.clone())
+                            .write(&mut reply)
+                            .unwrap();
+                    }
+                }
+            }
+            v4::Packet::PingReq => {
+                v4::PingResp.write(&mut reply).unwrap();
+            }
+            v4::Packet::Disconnect => return,
+            _ => {}
+        }
+
+        if socket.write_all(&reply).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn notification_payload(id: &str, dataset: &DatasetConfig, filename: &str) -> String {
+    serde_json::json!({
+        "specversion": "1.0",
+        "type": "nl.knmi.dataplatform.file.created.v1",
+        "source": "https://dataplatform.knmi.nl",
+        "id": id,
+        "time": "2025-01-03T14:56:43Z",
+        "datacontenttype": "application/json",
+        "data": {
+            "datasetName": dataset.dataset_name,
+            "datasetVersion": dataset.version,
+            "filename": filename,
+            "url": format!(
+                "https://api.dataplatform.knmi.nl/open-data/v1/datasets/{}/versions/{}/files/{}/url",
+                dataset.dataset_name, dataset.version, filename
+            )
+        }
+    })
+    .to_string()
+}
+
+fn created_topic(dataset: &DatasetConfig) -> String {
+    format!(
+        "dataplatform/file/v1/{}/{}/created",
+        dataset.dataset_name, dataset.version
+    )
+}
+
+/// The topic filter the service subscribes to for the dataset.
+fn dataset_filter(dataset: &DatasetConfig) -> String {
+    format!(
+        "dataplatform/file/v1/{}/{}/#",
+        dataset.dataset_name, dataset.version
+    )
+}
+
+/// Collect events until the given number of notifications is received.
+async fn collect_events(
+    stream: &mut NotificationStream,
+    notifications: usize,
+) -> Vec<NotificationEvent> {
+    let mut events = Vec::new();
+    let mut received = 0;
+
+    while received < notifications {
+        let event = tokio::time::timeout(TIMEOUT, stream.next())
+            .await
+            .expect("timeout waiting for notification")
+            .expect("stream ended");
+
+        if matches!(event, NotificationEvent::Notification { .. }) {
+            received += 1;
+        }
+        events.push(event);
+    }
+
+    events
+}
+
+fn notifications(events: &[NotificationEvent]) -> Vec<(String, String)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            NotificationEvent::Notification {
+                dataset,
+                notification,
+                ..
+            } => Some((
+                dataset.dataset_name.clone(),
+                notification.data.filename.clone(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_receive_notifications() {
+    let radar = KnownDataset::NowcastPrecipitation.config();
+    let observations = KnownDataset::ActualSynopticObservations.config();
+    let harmonie = KnownDataset::HarmonieCy43P1.config();
+    let messages = vec![
+        (
+            created_topic(&radar),
+            notification_payload("1", &radar, "RAD_NL25_RAC_FM_202501031455.h5"),
+        ),
+        (created_topic(&radar), "not a notification".to_string()),
+        (
+            created_topic(&harmonie),
+            notification_payload("2", &harmonie, "HARM43_V1_P1_2025010312.tar"),
+        ),
+        (
+            created_topic(&observations),
+            notification_payload(
+                "3",
+                &observations,
+                "KMDS__OPER_P___10M_OBS_L2_202501031450.nc",
+            ),
+        ),
+    ];
+    let broker = Broker::start(messages, 0).await;
+    let service = NotificationService::new(broker.config(vec![radar, observations]));
+    let mut stream = service.stream("client".to_string(), true);
+
+    let events = collect_events(&mut stream, 2).await;
+
+    let states: Vec<ConnectionState> = events
+        .iter()
+        .filter_map(|event| match event {
+            NotificationEvent::StateChanged(state) => Some(*state),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        states,
+        vec![
+            ConnectionState::Connecting,
+            ConnectionState::Connected,
+            ConnectionState::Subscribed
+        ]
+    );
+    assert_eq!(
+        notifications(&events),
+        vec![
+            (
+                "radar_forecast".to_string(),
+                "RAD_NL25_RAC_FM_202501031455.h5".to_string()
+            ),
+            (
+                "Actuele10mindataKNMIstations".to_string(),
+                "KMDS__OPER_P___10M_OBS_L2_202501031450.nc".to_string()
+            ),
+        ]
+    );
+    assert!(events.iter().any(|event| matches!(
+        event,
+        NotificationEvent::ParseError { payload, .. } if payload == "not a notification"
+    )));
+    assert_eq!(stream.state(), ConnectionState::Subscribed);
+
+    stream.stop();
+    let mut last = None;
+    while let Some(event) = stream.next().await {
+        last = Some(event);
+    }
+    assert!(matches!(
+        last,
+        Some(NotificationEvent::StateChanged(ConnectionState::Stopped))
+    ));
+}
+
+#[tokio::test]
+async fn test_rejected_subscriptions() {
+    let radar = KnownDataset::NowcastPrecipitation.config();
+    let harmonie = KnownDataset::HarmonieCy43P1.config();
+    let messages = vec![(
+        created_topic(&radar),
+        notification_payload("1", &radar, "RAD_NL25_RAC_FM_202501031455.h5"),
+    )];
+    let broker =
+        Broker::start_rejecting(messages.clone(), 0, vec![dataset_filter(&harmonie)]).await;
+    let service = NotificationService::new(broker.config(vec![radar.clone(), harmonie.clone()]));
+    let mut stream = service.stream("client".to_string(), true);
+
+    // the granted topic is subscribed, the rejected topic is reported
+    let events = collect_events(&mut stream, 1).await;
+    assert!(events.iter().any(|event| matches!(
+        event,
+        NotificationEvent::Error(NotificationError::SubscriptionError(e))
+            if e.contains(&dataset_filter(&harmonie)) && !e.contains(&dataset_filter(&radar))
+    )));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        NotificationEvent::StateChanged(ConnectionState::Subscribed)
+    )));
+    stream.stop();
+    while stream.next().await.is_some() {}
+
+    // no topic is granted, the stream is not subscribed
+    let broker = Broker::start_rejecting(messages, 0, vec![dataset_filter(&radar)]).await;
+    let service = NotificationService::new(broker.config(vec![radar.clone()]));
+    let mut stream = service.stream("client".to_string(), true);
+    loop {
+        let event = tokio::time::timeout(TIMEOUT, stream.next())
+            .await
+            .expect("timeout waiting for subscription error")
+            .expect("stream ended");
+        match event {
+            NotificationEvent::Error(NotificationError::SubscriptionError(e)) => {
+                assert!(e.contains(&dataset_filter(&radar)));
+                break;
+            }
+            NotificationEvent::StateChanged(state) => {
+                assert_ne!(state, ConnectionState::Subscribed)
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+    assert_eq!(stream.state(), ConnectionState::Connected);
+}
+
+#[tokio::test]
+async fn test_connect_options() {
+    let radar = KnownDataset::NowcastPrecipitation.config();
+    let messages = vec![(
+        created_topic(&radar),
+        notification_payload("1", &radar, "RAD_NL25_RAC_FM_202501031455.h5"),
+    )];
+    let mut broker = Broker::start(messages, 0).await;
+    let config = broker
+        .config(vec![radar])
+        .with_keep_alive(Duration::from_secs(30));
+    let service = NotificationService::new(config);
+    let mut stream = service.stream("client".to_string(), false);
+
+    collect_events(&mut stream, 1).await;
+
+    let connect = broker.connects.recv().await.unwrap();
+    assert_eq!(connect.client_id, "client");
+    assert_eq!(connect.keep_alive, 30);
+    assert!(!connect.clean_session);
+    let login = connect.login.unwrap();
+    assert_eq!(login.username, "client");
+    assert_eq!(login.password, "api-key");
+}
+
+#[tokio::test]
+async fn test_reconnect() {
+    let radar = KnownDataset::NowcastPrecipitation.config();
+    let messages = vec![(
+        created_topic(&radar),
+        notification_payload("1", &radar, "RAD_NL25_RAC_FM_202501031455.h5"),
+    )];
+    let broker = Broker::start(messages, 2).await;
+    let service = NotificationService::new(broker.config(vec![radar]));
+    let mut stream = service.stream("client".to_string(), true);
+
+    let events = collect_events(&mut stream, 1).await;
+
+    let failures: Vec<u32> = events
+        .iter()
+        .filter_map(|event| match event {
+            NotificationEvent::Error(NotificationError::ConnectionFailed(failure)) => {
+                Some(failure.attempt)
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(failures, vec![1, 2]);
+    assert_eq!(notifications(&events).len(), 1);
+}
+
+/// Acknowledge the notifications in the events with the given filenames.
+async fn acknowledge(store: &NotificationStore, events: &[NotificationEvent], filenames: &[&str]) {
+    for event in events {
+        if let NotificationEvent::Notification { notification, .. } = event {
+            if filenames.contains(&notification.data.filename.as_str()) {
+                assert!(store.acknowledge(notification).await.unwrap());
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_store_suppresses_redelivered_notifications() {
+    let radar = KnownDataset::NowcastPrecipitation.config();
+    let message = |id: &str, filename: &str| {
+        (
+            created_topic(&radar),
+            notification_payload(id, &radar, filename),
+        )
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let store_path = dir.path().join("store.json");
+
+    let broker = Broker::start(vec![message("1", "A.h5"), message("2", "B.h5")], 0).await;
+    let store = Arc::new(NotificationStore::open(&store_path).unwrap());
+    let service =
+        NotificationService::new(broker.config(vec![radar.clone()])).with_store(store.clone());
+    let mut stream = service.stream("client".to_string(), false);
+    let events = collect_events(&mut stream, 2).await;
+    assert_eq!(
+        notifications(&events),
+        vec![
+            ("radar_forecast".to_string(), "A.h5".to_string()),
+            ("radar_forecast".to_string(), "B.h5".to_string()),
+        ]
+    );
+    // the handler of B failed, only A is acknowledged
+    acknowledge(&store, &events, &["A.h5"]).await;
+    stream.stop();
+    while stream.next().await.is_some() {}
+    drop(service);
+    drop(store);
+
+    // restart, the broker redelivers the notifications, only the unhandled ones are emitted
+    let broker = Broker::start(
+        vec![
+            message("1", "A.h5"),
+            message("2", "B.h5"),
+            message("3", "C.h5"),
+        ],
+        0,
+    )
+    .await;
+    let store = Arc::new(NotificationStore::open(&store_path).unwrap());
+    let service =
+        NotificationService::new(broker.config(vec![radar.clone()])).with_store(store.clone());
+    let mut stream = service.stream("client".to_string(), false);
+    let events = collect_events(&mut stream, 2).await;
+    assert_eq!(
+        notifications(&events),
+        vec![
+            ("radar_forecast".to_string(), "B.h5".to_string()),
+            ("radar_forecast".to_string(), "C.h5".to_string()),
+        ]
+    );
+    acknowledge(&store, &events, &["B.h5", "C.h5"]).await;
+    assert!(store.contains("2"));
+    assert!(store.last_event_time(&radar).is_some());
+}